@@ -0,0 +1,69 @@
+#version 450
+
+// position in the full sized row of the atlas in pixels
+layout(location = 0) in vec2 v_Texel;
+layout(location = 1) in vec3 v_Normal;
+layout(location = 2) in vec2 v_Light;
+// ambient occlusion, 0.0 in fully occluded corners
+layout(location = 3) in float v_Ao;
+
+layout(location = 0) out vec4 o_Target;
+
+layout(set = 2, binding = 0) uniform ChunkMaterial_color {
+    vec4 color;
+};
+layout(set = 2, binding = 1) uniform texture2D ChunkMaterial_texture;
+layout(set = 2, binding = 2) uniform sampler ChunkMaterial_texture_sampler;
+// direction towards the sun, intensity in w
+layout(set = 2, binding = 3) uniform ChunkMaterial_sun {
+    vec4 sun;
+};
+layout(set = 2, binding = 4) uniform ChunkMaterial_ambient {
+    vec4 ambient;
+};
+layout(set = 2, binding = 5) uniform ChunkMaterial_alpha_cutoff {
+    float alpha_cutoff;
+};
+
+// see `atlas::CELL` and `atlas::MIP_LEVELS`
+const float CELL = 24.0;
+const float MIP_LEVELS = 3.0;
+
+// samples the block atlas at the mip level that fits the size of the face on screen,
+// the levels lie in rows below each other and shrink the position in the atlas by half each
+vec4 sample_atlas(vec2 pixel) {
+    vec2 size = vec2(textureSize(sampler2D(ChunkMaterial_texture, ChunkMaterial_texture_sampler), 0));
+
+    float lod = log2(max(length(dFdx(pixel)), length(dFdy(pixel))));
+    float scale = exp2(-clamp(floor(lod), 0.0, MIP_LEVELS - 1.0));
+    vec2 mip = vec2(pixel.x * scale, CELL * (2.0 - 2.0 * scale) + pixel.y * scale);
+
+    return textureLod(sampler2D(ChunkMaterial_texture, ChunkMaterial_texture_sampler), mip / size, 0.0);
+}
+
+// every light level is 80% as bright as the one above
+float brightness(float level) {
+    return pow(0.8, 15.0 - level * 15.0);
+}
+
+void main() {
+    // keeps the shape of the terrain readable, tops are the brightest and bottoms the darkest
+    float shade = 0.8 + 0.2 * v_Normal.y - 0.1 * abs(v_Normal.z);
+
+    // skylight is as bright as the time of day, faces turned towards the sun get a bit more
+    float diffuse = max(dot(v_Normal, sun.xyz), 0.0);
+    vec3 daylight = ambient.rgb + vec3(1.0, 0.95, 0.85) * sun.w * (0.45 + 0.3 * diffuse);
+    vec3 sky_light = brightness(v_Light.r) * daylight * shade;
+
+    // torches and lava have a warm glow
+    vec3 block_light = vec3(1.0, 0.85, 0.6) * brightness(v_Light.g) * shade;
+    // corners enclosed by blocks get less light
+    vec3 light = max(max(sky_light, block_light) * (0.5 + 0.5 * v_Ao), vec3(0.02));
+
+    vec4 texel = sample_atlas(v_Texel);
+    // the transparent parts of leaves, plants and torches
+    if (texel.a < alpha_cutoff) {
+        discard;
+    }
+    o_Target = vec4(texel.rgb * color.rgb * light, texel.a * color.a);
+}
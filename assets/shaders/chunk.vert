@@ -0,0 +1,59 @@
+#version 450
+
+// packed by `mesh::pack_vertex`:
+// x: position in 16th of a block moved by 16 blocks, 10 bits per axis, ambient occlusion in the highest 2 bits
+// y: tile of the block atlas in 10 bits, u and v inside of the tile in 16th in 5 bits each, face in 3 bits
+// z: skylight and block light in 4 bits each, number of animation frames and frames per second in quarters in 8 bits each
+layout(location = 0) in uvec3 Vertex_Data;
+
+layout(location = 0) out vec2 v_Texel;
+layout(location = 1) out vec3 v_Normal;
+layout(location = 2) out vec2 v_Light;
+layout(location = 3) out float v_Ao;
+
+layout(set = 0, binding = 0) uniform CameraViewProj {
+    mat4 ViewProj;
+};
+layout(set = 1, binding = 0) uniform Transform {
+    mat4 Model;
+};
+layout(set = 2, binding = 6) uniform ChunkMaterial_time {
+    float time;
+};
+
+// see `atlas::TILE`, `atlas::PADDING` and `atlas::CELL`
+const float TILE = 16.0;
+const float PADDING = 4.0;
+const float CELL = 24.0;
+
+// normals in the order of `mesh::FACES`
+const vec3 NORMALS[6] = vec3[6](
+    vec3(0.0, -1.0, 0.0),
+    vec3(0.0, 1.0, 0.0),
+    vec3(0.0, 0.0, -1.0),
+    vec3(0.0, 0.0, 1.0),
+    vec3(-1.0, 0.0, 0.0),
+    vec3(1.0, 0.0, 0.0)
+);
+
+void main() {
+    uvec3 data = Vertex_Data;
+
+    vec3 position = vec3(data.x & 1023u, (data.x >> 10) & 1023u, (data.x >> 20) & 1023u) / 16.0 - 16.0;
+    float tile = float(data.y & 1023u);
+    vec2 uv = vec2((data.y >> 10) & 31u, (data.y >> 15) & 31u) / 16.0;
+    uint face = (data.y >> 20) & 7u;
+
+    // the frames of animated textures are the tiles following the first one in the atlas
+    float frames = float((data.z >> 8) & 255u);
+    float frames_per_second = float((data.z >> 16) & 255u) / 4.0;
+    float frame = mod(floor(time * frames_per_second), max(frames, 1.0));
+
+    // position in the full sized row of the atlas in pixels, see `chunk.frag` for the mip levels
+    v_Texel = vec2((tile + frame) * CELL + PADDING, PADDING) + uv * TILE;
+    v_Normal = NORMALS[min(face, 5u)];
+    // skylight is the upper and block light the lower half of the light byte, see `light.rs`
+    v_Light = vec2((data.z >> 4) & 15u, data.z & 15u) / 15.0;
+    v_Ao = float(data.x >> 30) / 3.0;
+    gl_Position = ViewProj * Model * vec4(position, 1.0);
+}
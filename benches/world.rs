@@ -0,0 +1,119 @@
+//! measures terrain generation, meshing and the remeshing after edits, run with `cargo bench`
+
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+
+use voxel::atlas::Tiles;
+use voxel::custom_model::CustomModels;
+use voxel::culling;
+use voxel::light;
+use voxel::mesh::{self, MeshPass};
+use voxel::world::{self, Chunk, World};
+
+// the world of `chunk::spawn_world`
+const SEED: u32 = 1457087;
+const SEA_LEVEL: i32 = 44;
+
+const PASSES: [MeshPass; 3] = [MeshPass::Opaque, MeshPass::Cutout, MeshPass::Translucent];
+
+// a lit world of 3x3x3 chunks around `center`, so that the center chunk has all of its neighbours
+fn world_around(center: [i32; 3]) -> World {
+    let mut world = World::new(SEED, SEA_LEVEL);
+    for x in -1..=1 {
+        for y in -1..=1 {
+            for z in -1..=1 {
+                let mut chunk = Chunk::new(center[0] + x, center[1] + y, center[2] + z);
+                world::generate_terrain(&mut chunk, SEED, SEA_LEVEL);
+                world.add_chunk(chunk);
+            }
+        }
+    }
+    light::light_world(&mut world);
+    world
+}
+
+// all render passes of a chunk, like `chunk::spawn_chunk_mesh`
+fn mesh_chunk(chunk: usize, world: &World, models: &CustomModels, tiles: &Tiles) -> usize {
+    PASSES
+        .iter()
+        .map(|pass| mesh::create_chunk_mesh(chunk, world, *pass, models, tiles).vertices.len())
+        .sum()
+}
+
+fn generation(c: &mut Criterion) {
+    let mut group = c.benchmark_group("generate_terrain");
+    for size in [1, 2, 4].iter() {
+        let chunks = size * size * size;
+        group.bench_with_input(BenchmarkId::from_parameter(chunks), size, |b, size| {
+            b.iter(|| {
+                let mut world = World::new(SEED, SEA_LEVEL);
+                for x in 0..*size {
+                    for y in 0..*size {
+                        for z in 0..*size {
+                            let mut chunk = Chunk::new(x, y, z);
+                            world::generate_terrain(&mut chunk, SEED, SEA_LEVEL);
+                            world.add_chunk(chunk);
+                        }
+                    }
+                }
+                world
+            });
+        });
+    }
+    group.finish();
+}
+
+fn meshing(c: &mut Criterion) {
+    let models = CustomModels::default();
+    let tiles = Tiles::default();
+
+    let mut group = c.benchmark_group("create_chunk_mesh");
+    // the terrain is about 48 blocks high, caves of crimson stone lie below it
+    for (name, center) in [("surface", [0, 1, 0]), ("underground", [0, -1, 0]), ("empty", [0, 4, 0])].iter() {
+        let world = world_around(*center);
+        let chunk = world.chunk_lookup[center];
+        group.bench_function(*name, |b| b.iter(|| mesh_chunk(chunk, &world, &models, &tiles)));
+    }
+    group.finish();
+}
+
+// the work that follows `controll::build`: relighting in `chunk::update_light`
+// and remeshing every dirty chunk in `chunk::remesh_chunks`
+fn editing(c: &mut Criterion) {
+    let models = CustomModels::default();
+    let tiles = Tiles::default();
+
+    let mut group = c.benchmark_group("edit_burst");
+    group.sample_size(10);
+    for edits in [1, 16, 64].iter() {
+        group.bench_with_input(BenchmarkId::from_parameter(edits), edits, |b, edits| {
+            b.iter_batched(
+                || world_around([0, 1, 0]),
+                |mut world| {
+                    // a line of blocks dug out of the surface across the chunk border
+                    for i in 0..*edits {
+                        world.set_block([i - 8, 47, 16], voxel::block::AIR);
+                    }
+                    for position in world.changes.drain(..).collect::<Vec<[i32; 3]>>() {
+                        light::update_block(&mut world, position);
+                    }
+
+                    let dirty: Vec<usize> = (0..world.chunk_index.len())
+                        .filter(|chunk| world.chunk_index[*chunk].dirty)
+                        .collect();
+                    let mut vertices = 0;
+                    for chunk in dirty {
+                        world.chunk_index[chunk].dirty = false;
+                        world.chunk_index[chunk].connections = culling::connectivity(&world.chunk_index[chunk]);
+                        vertices += mesh_chunk(chunk, &world, &models, &tiles);
+                    }
+                    vertices
+                },
+                BatchSize::LargeInput,
+            );
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, generation, meshing, editing);
+criterion_main!(benches);
@@ -0,0 +1,134 @@
+use std::collections::HashMap;
+
+// directory inside of `assets` with one png per block texture, named like the texture
+pub const DIRECTORY: &str = "textures/blocks";
+
+// size of a texture in pixels
+pub const TILE: u32 = 16;
+// pixels around every tile that repeat its border, so that filtering never reaches the next tile
+pub const PADDING: u32 = 4;
+pub const CELL: u32 = TILE + 2 * PADDING;
+// number of mip levels, each one lies in its own row below the previous one, see `chunk.frag`
+pub const MIP_LEVELS: u32 = 3;
+
+// name of the tile that is used for textures that do not exist
+pub const MISSING: &str = "missing";
+
+/// a texture read from `assets/textures/blocks`, animated textures have their frames below each other
+pub struct Source {
+    pub name: String,
+    pub width: u32,
+    pub height: u32,
+    // rgba with 8 bit per channel
+    pub data: Vec<u8>,
+}
+
+/// the tiles of the packed block textures by name, tile 0 is the texture of missing textures and
+/// the frames of animated textures are the tiles following their first frame,
+/// the cell of a tile is found by `chunk.vert` from its index
+#[derive(Default, Clone)]
+pub struct Tiles {
+    pub tiles: HashMap<String, u16>,
+}
+impl Tiles {
+    /// tile of a texture by its name
+    pub fn tile(&self, name: &str) -> u16 {
+        self.tiles.get(name).copied().unwrap_or(0)
+    }
+}
+
+/// the pixels of the packed block textures, rgba with 8 bit per channel in srgb
+pub struct PackedAtlas {
+    pub width: u32,
+    pub height: u32,
+    pub data: Vec<u8>,
+}
+
+// magenta and black checkerboard
+fn missing_texture() -> Source {
+    let mut data = Vec::with_capacity((TILE * TILE * 4) as usize);
+    for y in 0..TILE {
+        for x in 0..TILE {
+            let magenta = (x / 8 + y / 8) % 2 == 0;
+            data.extend_from_slice(if magenta { &[255, 0, 255, 255] } else { &[0, 0, 0, 255] });
+        }
+    }
+    Source {name: MISSING.to_string(), width: TILE, height: TILE, data}
+}
+
+// pixel of a tile with the padding around it, the padding repeats the border of the tile
+fn padded_pixel(source: &Source, frame: u32, x: u32, y: u32) -> [u8; 4] {
+    let x = x.saturating_sub(PADDING).min(TILE - 1);
+    let y = y.saturating_sub(PADDING).min(TILE - 1) + frame * TILE;
+    let i = ((y * source.width + x) * 4) as usize;
+    [source.data[i], source.data[i + 1], source.data[i + 2], source.data[i + 3]]
+}
+
+// halves the size of a square cell, the colors are weighted by their alpha
+// so that transparent pixels do not darken the edges of leaves and plants
+fn downsample(cell: &[[u8; 4]], size: usize) -> Vec<[u8; 4]> {
+    let half = size / 2;
+    let mut smaller = Vec::with_capacity(half * half);
+    for y in 0..half {
+        for x in 0..half {
+            let mut color = [0u32; 3];
+            let mut alpha = 0u32;
+            for (dx, dy) in [(0, 0), (1, 0), (0, 1), (1, 1)].iter() {
+                let pixel = cell[(y * 2 + dy) * size + x * 2 + dx];
+                for channel in 0..3 {
+                    color[channel] += pixel[channel] as u32 * pixel[3] as u32;
+                }
+                alpha += pixel[3] as u32;
+            }
+            let average = |channel: usize| color[channel].checked_div(alpha).unwrap_or(0) as u8;
+            smaller.push([average(0), average(1), average(2), (alpha / 4) as u8]);
+        }
+    }
+    smaller
+}
+
+/// packs the textures in a row of padded cells, the mip levels lie in rows below with cells of half the size,
+/// so that the cell of a tile at level `n` starts at the cell of level 0 divided by `2^n`
+pub fn pack(sources: &[Source]) -> (PackedAtlas, Tiles) {
+    let missing = missing_texture();
+    let mut frames: Vec<(&Source, u32)> = vec![(&missing, 0)];
+    let mut tiles = Tiles::default();
+
+    for source in sources.iter() {
+        tiles.tiles.insert(source.name.clone(), frames.len() as u16);
+        for frame in 0..source.height / TILE {
+            frames.push((source, frame));
+        }
+    }
+
+    let width = frames.len() as u32 * CELL;
+    let height: u32 = (0..MIP_LEVELS).map(|level| CELL >> level).sum();
+    let mut data = vec![0; (width * height * 4) as usize];
+
+    for (tile, (source, frame)) in frames.iter().enumerate() {
+        let tile = tile as u32;
+        let mut cell: Vec<[u8; 4]> = Vec::with_capacity((CELL * CELL) as usize);
+        for y in 0..CELL {
+            for x in 0..CELL {
+                cell.push(padded_pixel(source, *frame, x, y));
+            }
+        }
+
+        let mut row = 0;
+        for level in 0..MIP_LEVELS {
+            let size = CELL >> level;
+            if level > 0 {
+                cell = downsample(&cell, (size * 2) as usize);
+            }
+            for y in 0..size {
+                for x in 0..size {
+                    let i = (((row + y) * width + tile * size + x) * 4) as usize;
+                    data[i..i + 4].copy_from_slice(&cell[(y * size + x) as usize]);
+                }
+            }
+            row += size;
+        }
+    }
+
+    (PackedAtlas {width, height, data}, tiles)
+}
@@ -0,0 +1,34 @@
+//! dedicated server without a window, usage: `server [address]`, listens on 0.0.0.0:7878 by default
+
+use std::path::Path;
+
+use voxel::atlas::Tiles;
+use voxel::custom_model;
+use voxel::server::{Server, ServerSettings};
+
+const DEFAULT_ADDRESS: &str = "0.0.0.0:7878";
+
+fn main() {
+    let address = std::env::args().nth(1).unwrap_or_else(|| DEFAULT_ADDRESS.to_string());
+
+    // only the number of custom blocks is needed to check the edits of the players
+    let (models, warnings) = custom_model::load_models(Path::new("assets"), &Tiles::default());
+    for warning in warnings {
+        eprintln!("{}", warning);
+    }
+    let settings = ServerSettings {custom_blocks: models.models.len(), ..Default::default()};
+
+    let server = match Server::bind(&address, settings) {
+        Ok(server) => server,
+        Err(e) => {
+            eprintln!("could not listen on {}: {}", address, e);
+            std::process::exit(1);
+        }
+    };
+    println!("listening on {}", address);
+
+    if let Err(e) = server.run() {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+}
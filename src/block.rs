@@ -0,0 +1,151 @@
+// block ids stored in `Chunk::blocks`
+pub type BlockId = u16;
+
+pub const AIR: BlockId = 0;
+pub const GRASS: BlockId = 1;
+pub const DIRT: BlockId = 2;
+pub const STONE: BlockId = 3;
+pub const CRIMSON_STONE: BlockId = 4;
+pub const MAGMA: BlockId = 5;
+pub const WATER: BlockId = 6;
+pub const SAND: BlockId = 7;
+pub const LAVA: BlockId = 8;
+pub const GRAVEL: BlockId = 9;
+pub const TORCH: BlockId = 10;
+pub const STONE_SLAB: BlockId = 11;
+pub const STONE_STAIRS: BlockId = 12;
+pub const FENCE: BlockId = 13;
+pub const FLOWER: BlockId = 14;
+pub const GLASS: BlockId = 15;
+pub const LEAVES: BlockId = 16;
+pub const PORTAL: BlockId = 17;
+// the ids below are all built in blocks
+pub const BUILT_IN: BlockId = 18;
+// ids from here on belong to the blocks of `assets/models/blocks.json`, see `custom_model`
+pub const FIRST_CUSTOM: BlockId = 1024;
+
+// names of the built in blocks in the order of their ids, typed into the console
+pub const NAMES: [&str; BUILT_IN as usize] = [
+    "air", "grass", "dirt", "stone", "crimson_stone", "magma", "water", "sand", "lava", "gravel",
+    "torch", "stone_slab", "stone_stairs", "fence", "flower", "glass", "leaves", "portal",
+];
+
+/// the built in block with this name, see `NAMES`
+pub fn from_name(name: &str) -> Option<BlockId> {
+    NAMES.iter().position(|block| *block == name).map(|block| block as BlockId)
+}
+
+/// a block with its state, like the orientation or the fluid level
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Block {
+    pub id: BlockId,
+    pub state: u8,
+}
+impl Block {
+    pub fn new(id: BlockId) -> Self {
+        Block {id, state: 0}
+    }
+}
+
+/// name of the texture of the block in `assets/textures/blocks`, see `atlas::BlockAtlas::tile`
+pub fn texture(block: BlockId) -> &'static str {
+    match block {
+        GRASS => "grass",
+        DIRT => "dirt",
+        STONE => "stone",
+        CRIMSON_STONE => "crimson_stone",
+        MAGMA => "magma",
+        WATER => "water",
+        SAND => "sand",
+        LAVA => "lava",
+        GRAVEL => "gravel",
+        TORCH => "torch",
+        STONE_SLAB | STONE_STAIRS => "stone",
+        FENCE => "planks",
+        FLOWER => "flower",
+        GLASS => "glass",
+        LEAVES => "leaves",
+        PORTAL => "portal",
+        _ => crate::atlas::MISSING,
+    }
+}
+
+/// number of frames and frames per second of animated textures, the frames are stacked
+/// in the png of the texture and are advanced by the shader
+pub fn animation(block: BlockId) -> (u8, f32) {
+    match block {
+        WATER => (4, 4.0),
+        LAVA => (4, 1.5),
+        PORTAL => (4, 6.0),
+        _ => (1, 0.0),
+    }
+}
+
+/// the model a block is drawn with, see `model`
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Shape {
+    Empty,
+    Cube,
+    // the lower or upper half of a block
+    Slab,
+    // a slab with a quarter block on top of it, facing the side of the quarter
+    Stairs,
+    // a post that connects to the fences and cubes next to it
+    Fence,
+    // two crossed quads, used by plants
+    Cross,
+    // a model loaded from the assets
+    Custom,
+}
+
+pub fn shape(block: BlockId) -> Shape {
+    match block {
+        AIR => Shape::Empty,
+        STONE_SLAB => Shape::Slab,
+        STONE_STAIRS => Shape::Stairs,
+        FENCE => Shape::Fence,
+        FLOWER | TORCH => Shape::Cross,
+        _ if block >= FIRST_CUSTOM => Shape::Custom,
+        _ => Shape::Cube,
+    }
+}
+
+/// blocks that are drawn in the translucent render path
+pub fn is_translucent(block: BlockId) -> bool {
+    block == WATER || block == GLASS || block == PORTAL
+}
+
+/// blocks with fully transparent parts that are drawn in the cutout render path
+pub fn is_cutout(block: BlockId) -> bool {
+    block == LEAVES || shape(block) == Shape::Cross || shape(block) == Shape::Custom
+}
+
+/// blocks that flow, their level is stored in the block state
+pub fn is_fluid(block: BlockId) -> bool {
+    block == WATER || block == LAVA
+}
+
+/// blocks that fall down when nothing supports them
+pub fn has_gravity(block: BlockId) -> bool {
+    block == SAND || block == GRAVEL
+}
+
+/// blocks that falling blocks can rest on
+pub fn is_solid(block: BlockId) -> bool {
+    block != AIR && !is_fluid(block) && shape(block) != Shape::Cross
+}
+
+/// light level a block shines with
+pub fn light_emission(block: BlockId) -> u8 {
+    match block {
+        LAVA => 15,
+        TORCH => 14,
+        PORTAL => 11,
+        _ => 0,
+    }
+}
+
+/// blocks that fully hide the faces of their neighbours
+pub fn is_opaque(block: BlockId) -> bool {
+    shape(block) == Shape::Cube && !is_translucent(block) && !is_fluid(block) && !is_cutout(block)
+}
@@ -1,21 +1,40 @@
+use std::collections::HashSet;
+
+use bevy::app::AppExit;
 use bevy::prelude::*;
-use bevy::render::pipeline::PrimitiveTopology;
 
-use noise::NoiseFn;
-use noise::OpenSimplex;
-use noise::Seedable;
+use voxel::coords::BlockPos;
+use voxel::culling;
+use voxel::custom_model::CustomModels;
+use voxel::fluid::FluidSimulation;
+use voxel::light;
+use voxel::lod::{self, LodSettings};
+use voxel::mesh::{self, MeshPass};
+use voxel::save::WorldSave;
+use voxel::settings::Settings;
+use voxel::world::{self, Chunk, World};
 
-pub struct Chunk {
-    pub x: i32,
-    pub z: i32,
-    pub y: i32,
-    pub index: [[[u8; 32]; 32]; 32],
+use crate::controll;
+use crate::render::{self, BlockAtlas};
+use crate::Materials;
+
+/// sent whenever a block of the world gets replaced
+pub struct BlockChanged {
+    pub position: [i32; 3],
 }
-impl Chunk {
-    //creates a new empty chunk filled with air
-    pub fn new(x: i32, y: i32, z: i32) -> Self {
-        Chunk {x: x, y: y, z: z, index: [[[0u8; 32]; 32]; 32]}
-    }
+
+// seconds between two saves of the changed chunks
+const SAVE_INTERVAL: f64 = 5.0;
+// the heights of the chunks that are generated
+const HEIGHTS: std::ops::Range<i32> = -3..5;
+
+/// the world of a single player game, set from the command line
+pub struct WorldOptions {
+    pub seed: u32,
+    pub sea_level: i32,
+    // chunks around the spawn that are generated, measured horizontally
+    pub view_distance: i32,
+    pub save: Option<WorldSave>,
 }
 
 pub struct ChunkMesh {
@@ -25,612 +44,320 @@ pub struct ChunkMesh {
 }
 impl ChunkMesh {
     pub fn new(x: i32, y: i32, z: i32) -> Self {
-        ChunkMesh {x: x, y: y, z: z}
-    }
-}
-
-pub struct World {
-    pub chunk_index: Vec<Chunk>,
-    pub seed: u32,
-}
-impl World {
-    pub fn new(seed: u32) -> Self {
-        let chunk_index: Vec<Chunk> = Vec::new();
-
-        World {seed: seed, chunk_index: chunk_index}
+        ChunkMesh {x, y, z}
     }
 }
 
-pub fn get_chunk_index(
-    position: [i32; 3],
-    world: &World,
-) -> usize {
-    let mut index: usize = 0;
-
-    for i in 0..world.chunk_index.len() - 1 {
-        if world.chunk_index[i].x == position[0]
-        && world.chunk_index[i].y == position[1]
-        && world.chunk_index[i].z == position[2]
-        {
-            index = i;
+/// turns the changes recorded by `World::set_block` into `BlockChanged` events
+pub fn send_block_changes(
+    mut world: Query<&mut World, With<World>>,
+    mut block_changed: EventWriter<BlockChanged>,
+) {
+    for mut world in world.iter_mut() {
+        for position in world.changes.drain(..) {
+            block_changed.send(BlockChanged { position });
         }
     }
-    
-    index
 }
 
-use crate::controll;
-
 pub fn spawn_world(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,  
+    options: Res<WorldOptions>,
+    network: Option<Res<crate::network::Network>>,
 ) {
+    // a server decides about the terrain of its world
+    let (seed, sea_level) = match network {
+        Some(network) => (network.connection.welcome.seed, network.connection.welcome.sea_level),
+        None => (options.seed, options.sea_level),
+    };
+
     commands
         .spawn_bundle(PbrBundle {
             mesh: meshes.add(Mesh::from(shape::Plane{ size: 1.0 })),
             transform: Transform::from_translation(Vec3::new(0.0, 0.0, 0.0)),
             ..Default::default()
         })
-        .insert(World::new(1457087))
+        .insert(World::new(seed, sea_level))
         .insert(controll::Builder::new(0.0, 60.0, 0.0));
 }
 
+// chunks that were changed in an earlier run are loaded instead of being generated
+fn load_or_generate(options: &WorldOptions, world: &World, position: [i32; 3]) -> Chunk {
+    let saved = options.save.as_ref().and_then(|save| match save.load_chunk(position) {
+        Ok(chunk) => chunk,
+        Err(e) => {
+            warn!("generating the chunk at {:?} again, it could not be loaded: {}", position, e);
+            None
+        }
+    });
+    saved.unwrap_or_else(|| {
+        let mut chunk = Chunk::new(position[0], position[1], position[2]);
+        world::generate_terrain(&mut chunk, world.seed, world.sea_level);
+        chunk
+    })
+}
+
+/// generates the chunks around the spawn
 pub fn generate_spawn(
+    options: Res<WorldOptions>,
     mut world: Query<&mut World, With<World>>
 ) {
-    let radius = 2;
+    let radius = options.view_distance;
     for mut world in world.iter_mut() {
         for x in -radius..radius {
-            for y in -3..5 {
+            for y in HEIGHTS {
                 for z in -radius..radius {
-                    world.chunk_index.push(Chunk::new(x, y, z));
-
-                    let current_chunk: usize = world.chunk_index.len() - 1;
-                    world.chunk_index[current_chunk].index = generate_terrain(x, y, z, world.seed);
+                    let chunk = load_or_generate(&options, &world, [x, y, z]);
+                    world.add_chunk(chunk);
                 }
             }
         } 
+
+        light::light_world(&mut world);
     }
     
 }
 
-fn generate_terrain(
-    chunk_x: i32,
-    chunk_y: i32,
-    chunk_z: i32,
-    seed: u32,
-) -> [[[u8; 32]; 32]; 32] {
-    let mut terrain: [[[u8; 32]; 32]; 32] = [[[0u8; 32]; 32]; 32];
+/// generates the chunks that came into view when the view distance of the settings grew,
+/// chunks outside of a smaller view distance stay loaded until the next start
+pub fn extend_view_distance(
+    settings: Res<Settings>,
+    options: Res<WorldOptions>,
+    mut generated: Local<Option<i32>>,
+    mut world: Query<&mut World, With<World>>,
+) {
+    let radius = generated.unwrap_or(options.view_distance);
+    if settings.view_distance <= radius {
+        return;
+    }
+    *generated = Some(settings.view_distance);
 
-    let open_simplex = OpenSimplex::new();
-    open_simplex.set_seed(seed);
-    
-    for x in 0..32 {
-        for z in 0..32 {
-            //generates terrain with noise:
-            let height: i32 = (open_simplex.get([
-                ( (x as i32 + chunk_x * 32 as i32) as f32 / 15. ) as f64, 
-                ( (z as i32 + chunk_z * 32 as i32) as f32 / 15. ) as f64,
-            ]) * 15. + 48.0) as i32;
-
-            let height_stalagmites: i32 = (open_simplex.get([
-                ( (x as i32 + chunk_x * 5 as i32) as f32 / 5. ) as f64, 
-                ( (z as i32 + chunk_z * 5 as i32) as f32 / 5. ) as f64,
-            ]) * 42. + 15.) as i32;
-
-            //writes height into terrain block index:
-            for height_grass in height-3..height {
-                if height_grass >= (chunk_y*32) as i32
-                && height_grass <= (chunk_y*32 + 31) as i32 {
-                    terrain[x][(height_grass - (chunk_y*32) as i32) as usize][z] = 1;
+    for mut world in world.iter_mut() {
+        let mut added: Vec<[i32; 3]> = Vec::new();
+        for x in -settings.view_distance..settings.view_distance {
+            for y in HEIGHTS {
+                for z in -settings.view_distance..settings.view_distance {
+                    if world.get_chunk([x, y, z]).is_some() {
+                        continue;
+                    }
+                    let chunk = load_or_generate(&options, &world, [x, y, z]);
+                    world.add_chunk(chunk);
+                    added.push([x, y, z]);
                 }
             }
-            
+        }
+        relight(&mut world, &added);
+    }
+}
 
-            //generates dirt
-            for height_dirt in height-13..height-3 {
-                if height_dirt >= (chunk_y*32) as i32
-                && height_dirt <= (chunk_y*32 + 31) as i32 {
-                    terrain[x][(height_dirt - (chunk_y*32) as i32) as usize][z] = 2;
-                }
-            }
+/// lights whole chunks that were added or replaced together with the chunks next to them,
+/// whose light they change, and marks all of them for remeshing
+pub fn relight(world: &mut World, changed: &[[i32; 3]]) {
+    let mut lit: Vec<[i32; 3]> = Vec::new();
+    for position in changed {
+        for offset in [[0, 0, 0], [0, -1, 0], [0, 1, 0], [0, 0, -1], [0, 0, 1], [-1, 0, 0], [1, 0, 0]].iter() {
+            lit.push([position[0] + offset[0], position[1] + offset[1], position[2] + offset[2]]);
+        }
+    }
 
-            //generates stone
-            for height_stone in -5..height-13 {
-                if height_stone >= (chunk_y*32) as i32
-                && height_stone <= (chunk_y*32 + 31) as i32 {
-                    terrain[x][(height_stone - (chunk_y*32) as i32) as usize][z] = 3;
-                }
-            }
+    light::light_chunks(world, &lit);
+    for position in lit {
+        if let Some(chunk) = world.get_chunk_mut(position) {
+            chunk.dirty = true;
+        }
+    }
+}
 
-            //creates stalagmites
-            for stalagmite in height as i32 - 64..height as i32 / 2 - 22 {
-                if stalagmite >= (chunk_y*32) as i32
-                && stalagmite <= (chunk_y*32 + 31) as i32 {
-                    terrain[x][(stalagmite - (chunk_y*32) as i32) as usize][z] = 5;
-                }
-            }
-            for stalagmite in -height_stalagmites..height as i32 - 64 {
-                if stalagmite >= (chunk_y*32) as i32
-                && stalagmite <= (chunk_y*32 + 31) as i32 {
-                    terrain[x][(stalagmite - (chunk_y*32) as i32) as usize][z] = 4;
+/// writes the chunks with changed blocks to the world directory every few seconds and when the game closes
+pub fn save_chunks(
+    time: Res<Time>,
+    options: Res<WorldOptions>,
+    mut block_changed: EventReader<BlockChanged>,
+    mut app_exit: EventReader<AppExit>,
+    mut unsaved: Local<HashSet<[i32; 3]>>,
+    mut last_save: Local<f64>,
+    world: Query<&World>,
+) {
+    let save = match &options.save {
+        Some(save) => save,
+        None => return,
+    };
+    for event in block_changed.iter() {
+        unsaved.insert(BlockPos::from(event.position).chunk().into());
+    }
+
+    let exiting = app_exit.iter().next().is_some();
+    if !exiting && time.seconds_since_startup() - *last_save < SAVE_INTERVAL {
+        return;
+    }
+    *last_save = time.seconds_since_startup();
+
+    for world in world.iter() {
+        for position in unsaved.drain() {
+            if let Some(chunk) = world.get_chunk(position) {
+                if let Err(e) = save.save_chunk(chunk) {
+                    error!("could not save the chunk at {:?}: {}", position, e);
                 }
             }
+        }
+    }
+}
 
+/// relights the blocks replaced during this frame before their chunks are remeshed
+pub fn update_light(
+    mut world: Query<&mut World, With<World>>,
+) {
+    for mut world in world.iter_mut() {
+        let changes = world.changes.clone();
+        for position in changes {
+            light::update_block(&mut world, position);
         }
     }
-    terrain
 }
 
+/// wakes up the fluid around edited blocks
+pub fn activate_fluids(
+    mut events: EventReader<BlockChanged>,
+    mut simulation: ResMut<FluidSimulation>,
+) {
+    for event in events.iter() {
+        simulation.activate(event.position);
+    }
+}
 
-use bevy::render::mesh::Indices;
-use crate::Materials;
+/// advances all active fluid cells by one step, runs on a fixed timestep
+pub fn simulate_fluids(
+    mut world: Query<&mut World, With<World>>,
+    mut simulation: ResMut<FluidSimulation>,
+) {
+    for mut world in world.iter_mut() {
+        simulation.step(&mut world);
+    }
+}
 
+/// picks the level of every chunk by its distance to the camera and marks the changed ones for remeshing
+pub fn update_lod(
+    settings: Res<LodSettings>,
+    camera: Query<&Transform, With<crate::Camera>>,
+    mut world: Query<&mut World, With<World>>,
+) {
+    let camera = match camera.iter().next() {
+        Some(camera) => camera.translation,
+        None => return,
+    };
+
+    for mut world in world.iter_mut() {
+        lod::update_levels(&mut world, &settings, [camera.x, camera.z]);
+    }
+}
 
 pub fn render_chunk(
     mut commands: Commands,
     materials: Res<Materials>,
     mut meshes: ResMut<Assets<Mesh>>,
-    world: Query<&World, With<World>>,
+    models: Res<CustomModels>,
+    atlas: Res<BlockAtlas>,
+    mut world: Query<&mut World, With<World>>,
 ) {
-    for world in world.iter() {
-
-        for chunk in 0..world.chunk_index.len() - 1 {
-
-            
-            let mesh = create_chunk_mesh(chunk, world);
-
-
-            commands
-                .spawn_bundle(PbrBundle {
-                    mesh: meshes.add(mesh),
-                    material: materials.blocks.clone(),
-                    transform: Transform::from_matrix(Mat4::from_scale_rotation_translation(
-                        Vec3::splat(1.0),
-                        Quat::from_rotation_x(0.0),
-                        Vec3::new((world.chunk_index[chunk].x * 32) as f32, (world.chunk_index[chunk].y * 32) as f32, (world.chunk_index[chunk].z * 32) as f32),
-                    )),
-                    ..Default::default()
-                })
-                .insert(ChunkMesh::new(world.chunk_index[chunk].x, world.chunk_index[chunk].y, world.chunk_index[chunk].z));
-        }       
+    for mut world in world.iter_mut() {
+        for chunk in 0..world.chunk_index.len() {
+            world.chunk_index[chunk].connections = culling::connectivity(&world.chunk_index[chunk]);
+            spawn_chunk_mesh(chunk, &world, &mut commands, &materials, &mut meshes, &models, &atlas);
+        }
     }
 }
 
+// replaces the ChunkMesh of every chunk that changed since the last frame
+pub fn remesh_chunks(
+    mut commands: Commands,
+    materials: Res<Materials>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    models: Res<CustomModels>,
+    atlas: Res<BlockAtlas>,
+    mut world: Query<&mut World, With<World>>,
+    chunk_mesh: Query<(Entity, &ChunkMesh), With<ChunkMesh>>,
+) {
+    for mut world in world.iter_mut() {
+        let dirty: Vec<usize> = (0..world.chunk_index.len())
+            .filter(|chunk| world.chunk_index[*chunk].dirty)
+            .collect();
 
-pub fn create_chunk_mesh(
+        if dirty.is_empty() {
+            continue;
+        }
+
+        for (entity, chunk_mesh) in chunk_mesh.iter() {
+            if dirty.iter().any(|chunk| {
+                let chunk = &world.chunk_index[*chunk];
+                chunk.x == chunk_mesh.x && chunk.y == chunk_mesh.y && chunk.z == chunk_mesh.z
+            }) {
+                commands.entity(entity).despawn();
+            }
+        }
+
+        for chunk in dirty {
+            world.chunk_index[chunk].dirty = false;
+            world.chunk_index[chunk].connections = culling::connectivity(&world.chunk_index[chunk]);
+            spawn_chunk_mesh(chunk, &world, &mut commands, &materials, &mut meshes, &models, &atlas);
+        }
+    }
+}
+
+// spawns the opaque, the cutout and the translucent mesh of a chunk
+pub fn spawn_chunk_mesh(
     chunk: usize,
     world: &World,
-) -> Mesh {
-    let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
-    let v_length = 8*32*32*32;
-
-    let mut positions: Vec<[f32; 3]> = Vec::with_capacity(v_length);
-    let mut normals: Vec<[f32; 3]> = Vec::with_capacity(v_length);
-    let mut uvs: Vec<[f32; 2]> = Vec::with_capacity(v_length);
-
-    let mut indices: Vec<u32> = Vec::with_capacity(v_length);
-
-    for x1 in 0..32 {
-        for y1 in 0..32 {
-            for z1 in 0..32 {
-
-                let x: f32 = x1 as f32;
-                let y: f32 = y1 as f32;
-                let z: f32= z1 as f32;
-
-                let block: u8 = world.chunk_index[chunk].index[x1][y1][z1];
-
-                let world_x = world.chunk_index[chunk].x as f32 * 32.0 + x;
-                let world_y = world.chunk_index[chunk].y as f32 * 32.0 + y;
-                let world_z = world.chunk_index[chunk].z as f32 * 32.0 + z;
-                
-                if block != 0 { 
-
-                    //below plane
-                    if y as usize >= 1 {
-                        if world.chunk_index[chunk].index[x1][y1 - 1][z1] == 0 {
-                            // creates vertices
-                            positions.push([ x, y, z ]);
-                            normals.push([ world_x, world_y, world_z ]);
-                            uvs.push([ block as f32 / 256.0, 0.0 ]);
-
-                            positions.push([ x, y, z + 1.0 ]);
-                            normals.push([ world_x, world_y, world_z ]);
-                            uvs.push([ (block as f32 / 256.0) + 1.0 / 256.0, 0.0 ]);
-
-                            positions.push([ x + 1.0, y, z ]);
-                            normals.push([ world_x, world_y, world_z ]);
-                            uvs.push([ block as f32 / 256.0, 1.0 ]);
-
-                            positions.push([ x + 1.0, y, z + 1.0 ]);
-                            normals.push([ world_x, world_y, world_z ]);
-                            uvs.push([ (block as f32 / 256.0) + 1.0 / 256.0, 1.0 ]);
-
-                            // creates indices
-                            let positions_len = positions.len() - 4;
-                            indices.push( (positions_len + 0) as u32 );
-                            indices.push( (positions_len + 2) as u32 );
-                            indices.push( (positions_len + 1) as u32 );
-
-
-                            indices.push( (positions_len + 3) as u32 );
-                            indices.push( (positions_len + 1) as u32 );
-                            indices.push( (positions_len + 2) as u32 );
-                        }
-                        
-                    } else {
-                        // creates vertices
-                        positions.push([ x, y, z ]);
-                        normals.push([ world_x, world_y, world_z ]);
-                        uvs.push([ block as f32 / 256.0, 0.0 ]);
-
-                        positions.push([ x, y, z + 1.0 ]);
-                        normals.push([ world_x, world_y, world_z ]);
-                        uvs.push([ (block as f32 / 256.0) + 1.0 / 256.0, 0.0 ]);
-
-                        positions.push([ x + 1.0, y, z ]);
-                        normals.push([ world_x, world_y, world_z ]);
-                        uvs.push([ block as f32 / 256.0, 1.0 ]);
-
-                        positions.push([ x + 1.0, y, z + 1.0 ]);
-                        normals.push([ world_x, world_y, world_z ]);
-                        uvs.push([ (block as f32 / 256.0) + 1.0 / 256.0, 1.0 ]);
-
-                        // creates indices
-                        let positions_len = positions.len() - 4;
-                        indices.push( (positions_len + 0) as u32 );
-                        indices.push( (positions_len + 2) as u32 );
-                        indices.push( (positions_len + 1) as u32 );
-
-                        indices.push( (positions_len + 3) as u32 );
-                        indices.push( (positions_len + 1) as u32 );
-                        indices.push( (positions_len + 2) as u32 );
-                    }
-
-                    //above plane
-                    if y as usize <= 30 {
-                        if world.chunk_index[chunk].index[x1][y1 + 1][z1] == 0 {
-                            // creates vertices
-                            positions.push([ x, y + 1.0, z ]);
-                            normals.push([ world_x, world_y, world_z ]);
-                            uvs.push([ block as f32 / 256.0, 0.0 ]);
-
-                            positions.push([ x, y + 1.0, z + 1.0 ]);
-                            normals.push([ world_x, world_y, world_z ]);
-                            uvs.push([ (block as f32 / 256.0) + 1.0 / 256.0, 0.0 ]);
-
-                            positions.push([ x + 1.0, y + 1.0, z ]);
-                            normals.push([ world_x, world_y, world_z ]);
-                            uvs.push([ block as f32 / 256.0, 1.0 ]);
-
-                            positions.push([ x + 1.0, y + 1.0, z + 1.0] );
-                            normals.push([ world_x, world_y, world_z ]);
-                            uvs.push([ (block as f32 / 256.0) + 1.0 / 256.0, 1.0 ]);
-
-                            // creates indices
-                            let positions_len = positions.len() - 4;
-                            indices.push( (positions_len + 2) as u32 );
-                            indices.push( (positions_len + 0) as u32 );
-                            indices.push( (positions_len + 3) as u32 );
-
-                            indices.push( (positions_len + 0) as u32 );
-                            indices.push( (positions_len + 1) as u32 );
-                            indices.push( (positions_len + 3) as u32 );
-                        }
-                        
-                    } else {
-                        // creates vertices
-                        positions.push([ x, y + 1.0, z ]);
-                        normals.push([ world_x, world_y, world_z ]);
-                        uvs.push([ block as f32 / 256.0, 0.0 ]);
-
-                        positions.push([ x, y + 1.0, z + 1.0 ]);
-                        normals.push([ world_x, world_y, world_z ]);
-                        uvs.push([ (block as f32 / 256.0) + 1.0 / 256.0, 0.0 ]);
-
-                        positions.push([ x + 1.0, y + 1.0, z ]);
-                        normals.push([ world_x, world_y, world_z ]);
-                        uvs.push([ block as f32 / 256.0, 1.0 ]);
-
-                        positions.push([ x + 1.0, y + 1.0, z + 1.0] );
-                        normals.push([ world_x, world_y, world_z ]);
-                        uvs.push([ (block as f32 / 256.0) + 1.0 / 256.0, 1.0 ]);
-
-                        // creates indices
-                        let positions_len = positions.len() - 4;
-                        indices.push( (positions_len + 2) as u32 );
-                        indices.push( (positions_len + 0) as u32 );
-                        indices.push( (positions_len + 3) as u32 );
-
-                        indices.push( (positions_len + 0) as u32 );
-                        indices.push( (positions_len + 1) as u32 );
-                        indices.push( (positions_len + 3) as u32 );
-                    }
+    commands: &mut Commands,
+    materials: &Materials,
+    meshes: &mut Assets<Mesh>,
+    models: &CustomModels,
+    atlas: &BlockAtlas,
+) {
+    let position = [world.chunk_index[chunk].x, world.chunk_index[chunk].y, world.chunk_index[chunk].z];
+    let transform = Transform::from_translation(Vec3::new(
+        (position[0] * 32) as f32,
+        (position[1] * 32) as f32,
+        (position[2] * 32) as f32,
+    ));
+
+    // distant chunks are built from downsampled blocks
+    let create_mesh = |pass| {
+        if world.chunk_index[chunk].lod == 0 {
+            mesh::create_chunk_mesh(chunk, world, pass, models, &atlas.tiles)
+        } else {
+            lod::create_lod_mesh(chunk, world, pass, &atlas.tiles)
+        }
+    };
 
-                    //left plane
-                    if z as usize >= 1 {
-                        if world.chunk_index[chunk].index[x1][y1][z1 - 1] == 0 {
-                            // creates vertices
-                            positions.push([ x, y, z ]);
-                            normals.push([ world_x, world_y, world_z ]);
-                            uvs.push([ block as f32 / 256.0, 0.0 ]);
-
-                            positions.push([ x + 1.0, y, z ]);
-                            normals.push([ world_x, world_y, world_z ]);
-                            uvs.push([ (block as f32 / 256.0) + 1.0 / 256.0, 0.0 ]);
-
-                            positions.push([ x, y + 1.0, z ]);
-                            normals.push([ world_x, world_y, world_z ]);
-                            uvs.push([ block as f32 / 256.0, 1.0 ]);
-
-                            positions.push([ x + 1.0, y + 1.0, z ]);
-                            normals.push([ world_x, world_y, world_z ]);
-                            uvs.push([ (block as f32 / 256.0) + 1.0 / 256.0, 1.0 ]);
-
-                            // creates indices
-                            let positions_len = positions.len() - 4;
-                            indices.push( (positions_len + 1) as u32 );
-                            indices.push( (positions_len + 0) as u32 );
-                            indices.push( (positions_len + 3) as u32 );
-
-                            indices.push( (positions_len + 0) as u32 );
-                            indices.push( (positions_len + 2) as u32 );
-                            indices.push( (positions_len + 3) as u32 );
-                        }
-                    } else {
-                        // creates vertices
-                        positions.push([ x, y, z ]);
-                        normals.push([ world_x, world_y, world_z ]);
-                        uvs.push([ block as f32 / 256.0, 0.0 ]);
-
-                        positions.push([ x + 1.0, y, z ]);
-                        normals.push([ world_x, world_y, world_z ]);
-                        uvs.push([ (block as f32 / 256.0) + 1.0 / 256.0, 0.0 ]);
-
-                        positions.push([ x, y + 1.0, z ]);
-                        normals.push([ world_x, world_y, world_z ]);
-                        uvs.push([ block as f32 / 256.0, 1.0 ]);
-
-                        positions.push([ x + 1.0, y + 1.0, z ]);
-                        normals.push([ world_x, world_y, world_z ]);
-                        uvs.push([ (block as f32 / 256.0) + 1.0 / 256.0, 1.0 ]);
-
-                        // creates indices
-                        let positions_len = positions.len() - 4;
-                        indices.push( (positions_len + 1) as u32 );
-                        indices.push( (positions_len + 0) as u32 );
-                        indices.push( (positions_len + 3) as u32 );
-
-                        indices.push( (positions_len + 0) as u32 );
-                        indices.push( (positions_len + 2) as u32 );
-                        indices.push( (positions_len + 3) as u32 );
-                    }
+    let passes = [
+        (MeshPass::Opaque, materials.blocks.clone()),
+        (MeshPass::Cutout, materials.cutout_blocks.clone()),
+        (MeshPass::Translucent, materials.translucent_blocks.clone()),
+    ];
 
-                    //right plane
-                    if z as usize <= 30 {
-                        if world.chunk_index[chunk].index[x1][y1][z1 + 1] == 0 {
-                            // creates vertices
-                            positions.push([ x, y, z + 1.0 ]);
-                            normals.push([ world_x, world_y, world_z ]);
-                            uvs.push([ block as f32 / 256.0, 0.0 ]);
-
-                            positions.push([ x + 1.0, y, z + 1.0 ]);
-                            normals.push([ world_x, world_y, world_z ]);
-                            uvs.push([ (block as f32 / 256.0) + 1.0 / 256.0, 0.0 ]);
-
-                            positions.push([ x, y + 1.0, z + 1.0 ]);
-                            normals.push([ world_x, world_y, world_z ]);
-                            uvs.push([ block as f32 / 256.0, 1.0 ]);
-
-                            positions.push([ x + 1.0, y + 1.0, z + 1.0 ]);
-                            normals.push([ world_x, world_y, world_z ]);
-                            uvs.push([ (block as f32 / 256.0) + 1.0 / 256.0, 1.0 ]);
-
-                            // creates indices
-                            let positions_len = positions.len() - 4;
-                            indices.push( (positions_len + 0) as u32 );
-                            indices.push( (positions_len + 1) as u32 );
-                            indices.push( (positions_len + 3) as u32 );
-
-                            indices.push( (positions_len + 2) as u32 );
-                            indices.push( (positions_len + 0) as u32 );
-                            indices.push( (positions_len + 3) as u32 );
-                        }
-                    } else {
-                        // creates vertices
-                        positions.push([ x, y, z + 1.0 ]);
-                        normals.push([ world_x, world_y, world_z ]);
-                        uvs.push([ block as f32 / 256.0, 0.0 ]);
-
-                        positions.push([ x + 1.0, y, z + 1.0 ]);
-                        normals.push([ world_x, world_y, world_z ]);
-                        uvs.push([ (block as f32 / 256.0) + 1.0 / 256.0, 0.0 ]);
-
-                        positions.push([ x, y + 1.0, z + 1.0 ]);
-                        normals.push([ world_x, world_y, world_z ]);
-                        uvs.push([ block as f32 / 256.0, 1.0 ]);
-
-                        positions.push([ x + 1.0, y + 1.0, z + 1.0 ]);
-                        normals.push([ world_x, world_y, world_z ]);
-                        uvs.push([ (block as f32 / 256.0) + 1.0 / 256.0, 1.0 ]);
-
-                        // creates indices
-                        let positions_len = positions.len() - 4;
-                        indices.push( (positions_len + 0) as u32 );
-                        indices.push( (positions_len + 1) as u32 );
-                        indices.push( (positions_len + 3) as u32 );
-
-                        indices.push( (positions_len + 2) as u32 );
-                        indices.push( (positions_len + 0) as u32 );
-                        indices.push( (positions_len + 3) as u32 );
-                    }
+    for (pass, material) in passes.iter() {
+        let mut buffers = create_mesh(*pass);
 
-                    
-                    //front plane
-                    if x as usize >= 1 {
-                        if world.chunk_index[chunk].index[x1 - 1][y1][z1] == 0 {
-                            // creates vertices
-                            positions.push([ x, y, z ]);
-                            normals.push([ world_x, world_y, world_z ]);
-                            uvs.push([ block as f32 / 256.0, 0.0 ]);
-
-                            positions.push([ x, y, z + 1.0 ]);
-                            normals.push([ world_x, world_y, world_z ]);
-                            uvs.push([ (block as f32 / 256.0) + 1.0 / 256.0, 0.0 ]);
-
-                            positions.push([ x, y + 1.0, z ]);
-                            normals.push([ world_x, world_y, world_z ]);
-                            uvs.push([ block as f32 / 256.0, 1.0 ]);
-
-                            positions.push([ x, y + 1.0, z + 1.0 ]);
-                            normals.push([ world_x, world_y, world_z ]);
-                            uvs.push([ (block as f32 / 256.0) + 1.0 / 256.0, 1.0 ]);
-
-                            // creates indices
-                            let positions_len = positions.len() - 4;
-                            indices.push( (positions_len + 0) as u32 );
-                            indices.push( (positions_len + 1) as u32 );
-                            indices.push( (positions_len + 2) as u32 );
-
-                            indices.push( (positions_len + 2) as u32 );
-                            indices.push( (positions_len + 1) as u32 );
-                            indices.push( (positions_len + 3) as u32 ); 
-                        }
-                    } else {
-                        // creates vertices
-                        positions.push([ x, y, z ]);
-                        normals.push([ world_x, world_y, world_z ]);
-                        uvs.push([ block as f32 / 256.0, 0.0 ]);
-
-                        positions.push([ x, y, z + 1.0 ]);
-                        normals.push([ world_x, world_y, world_z ]);
-                        uvs.push([ (block as f32 / 256.0) + 1.0 / 256.0, 0.0 ]);
-
-                        positions.push([ x, y + 1.0, z ]);
-                        normals.push([ world_x, world_y, world_z ]);
-                        uvs.push([ block as f32 / 256.0, 1.0 ]);
-
-                        positions.push([ x, y + 1.0, z + 1.0 ]);
-                        normals.push([ world_x, world_y, world_z ]);
-                        uvs.push([ (block as f32 / 256.0) + 1.0 / 256.0, 1.0 ]);
-
-                        // creates indices
-                        let positions_len = positions.len() - 4;
-                        indices.push( (positions_len + 0) as u32 );
-                        indices.push( (positions_len + 1) as u32 );
-                        indices.push( (positions_len + 2) as u32 );
-
-                        indices.push( (positions_len + 2) as u32 );
-                        indices.push( (positions_len + 1) as u32 );
-                        indices.push( (positions_len + 3) as u32 ); 
-                    }
-                    
-                    //back plane
-                    if x as usize <= 30 {
-                        if world.chunk_index[chunk].index[x1 + 1][y1][z1] == 0 {
-                            // creates vertices
-                            positions.push([ x + 1.0, y, z ]);
-                            normals.push([ world_x, world_y, world_z ]);
-                            uvs.push([ block as f32 / 256.0, 0.0 ]);
-
-                            positions.push([ x + 1.0, y, z + 1.0 ]);
-                            normals.push([ world_x, world_y, world_z ]);
-                            uvs.push([ (block as f32 / 256.0) + 1.0 / 256.0, 0.0 ]);
-
-                            positions.push([ x + 1.0, y + 1.0, z ]);
-                            normals.push([ world_x, world_y, world_z ]);
-                            uvs.push([ block as f32 / 256.0, 1.0 ]);
-
-                            positions.push([ x + 1.0, y + 1.0, z + 1.0 ]);
-                            normals.push([ world_x, world_y, world_z ]);
-                            uvs.push([ (block as f32 / 256.0) + 1.0 / 256.0, 1.0 ]);
-                            
-                            // creates indices
-                            let positions_len = positions.len() - 4;
-                            indices.push( (positions_len + 1) as u32 );
-                            indices.push( (positions_len + 0) as u32 );
-                            indices.push( (positions_len + 2) as u32 );
-
-                            indices.push( (positions_len + 1) as u32 );
-                            indices.push( (positions_len + 2) as u32 );
-                            indices.push( (positions_len + 3) as u32 );
-                        }
-                    } else {
-                        // creates vertices
-                        positions.push([ x + 1.0, y, z ]);
-                        normals.push([ world_x, world_y, world_z ]);
-                        uvs.push([ block as f32 / 256.0, 0.0 ]);
-
-                        positions.push([ x + 1.0, y, z + 1.0 ]);
-                        normals.push([ world_x, world_y, world_z ]);
-                        uvs.push([ (block as f32 / 256.0) + 1.0 / 256.0, 0.0 ]);
-
-                        positions.push([ x + 1.0, y + 1.0, z ]);
-                        normals.push([ world_x, world_y, world_z ]);
-                        uvs.push([ block as f32 / 256.0, 1.0 ]);
-
-                        positions.push([ x + 1.0, y + 1.0, z + 1.0 ]);
-                        normals.push([ world_x, world_y, world_z ]);
-                        uvs.push([ (block as f32 / 256.0) + 1.0 / 256.0, 1.0 ]);
-                            
-                        // creates indices
-                        let positions_len = positions.len() - 4;
-                        indices.push( (positions_len + 1) as u32 );
-                        indices.push( (positions_len + 0) as u32 );
-                        indices.push( (positions_len + 2) as u32 );
-
-                        indices.push( (positions_len + 1) as u32 );
-                        indices.push( (positions_len + 2) as u32 );
-                        indices.push( (positions_len + 3) as u32 );
-                    }
-                }
-            }
+        // most chunks do not contain any leaves, plants, glass or water
+        if buffers.vertices.is_empty() {
+            continue;
         }
-    }
 
-    mesh.set_attribute(Mesh::ATTRIBUTE_POSITION, positions);
-    mesh.set_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
-    mesh.set_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+        let translucent = *pass == MeshPass::Translucent;
+        let mut transform = transform;
 
-    mesh.set_indices(Some(Indices::U32(indices)));
-    
-    mesh
-}
-
-pub fn get_chunk_coordinates_from_position(
-    pos: [i32; 3],
-) -> [i32; 3] {
-    let mut final_pos: [i32; 3] = [0, 0, 0];
-    
-    if pos[0] < 0 {
-        final_pos[0] = ( pos[0] + 1 ) / 32;
-    }
-    else {
-        final_pos[0] = pos[0] / 32;
-    }
-    if pos[1] < 0 {
-        final_pos[1] = ( pos[1] + 1 ) / 32;
-    }
-    else {
-        final_pos[1] = pos[1] / 32;
-    }
-    if pos[2] < 0 {
-        final_pos[2] = ( pos[2] + 1 ) / 32;
-    }
-    else {
-        final_pos[2] = pos[2] / 32;
-    }
+        // translucent chunks are sorted by their translation, so it is moved to the center of the chunk
+        if translucent {
+            transform.translation += Vec3::splat(16.0);
+            for vertex in buffers.vertices.iter_mut() {
+                *vertex = mesh::translate_vertex(*vertex, [-16, -16, -16]);
+            }
+        }
 
-    if pos[0] < 0 {
-        final_pos[0]-=1;
-    }
-    if pos[1] < 0 {
-        final_pos[1]-=1;
+        commands
+            .spawn_bundle(render::chunk_bundle(meshes.add(render::packed_mesh(buffers)), materials, transform, translucent))
+            .insert(material.clone())
+            .insert(ChunkMesh::new(position[0], position[1], position[2]));
     }
-    if pos[2] < 0 {
-        final_pos[2]-=1;
-    }
-
-    final_pos
-}
\ No newline at end of file
+}
@@ -0,0 +1,185 @@
+use std::path::PathBuf;
+
+use clap::{value_parser, Arg, ArgAction, Command};
+
+use voxel::settings::{Settings, MAX_VIEW_DISTANCE, MIN_VIEW_DISTANCE};
+
+/// the command line of the game, `voxel --help` lists everything,
+/// the options that are also settings override the settings file for this run
+#[derive(Debug)]
+pub struct Options {
+    // the seed of new worlds, existing worlds keep their own
+    pub seed: u32,
+    pub world: Option<PathBuf>,
+    pub settings: PathBuf,
+    // chunks around the spawn that are generated, measured horizontally
+    pub view_distance: Option<i32>,
+    // width and height in pixels
+    pub window: Option<[u32; 2]>,
+    pub fly_speed: Option<f32>,
+    pub sensitivity: Option<f32>,
+    // runs a server without a window instead of the game
+    pub headless: bool,
+    pub listen: String,
+    pub connect: Option<String>,
+    pub name: String,
+}
+
+fn command() -> Command<'static> {
+    Command::new("voxel")
+        .about("a voxel game, runs a dedicated server with --headless")
+        .arg(Arg::new("seed")
+            .long("seed")
+            .value_name("SEED")
+            .default_value("1457087")
+            .value_parser(value_parser!(u32))
+            .help("the seed of new worlds, existing worlds keep their own"))
+        .arg(Arg::new("world")
+            .long("world")
+            .value_name("DIR")
+            .value_parser(value_parser!(PathBuf))
+            .conflicts_with("connect")
+            .help("keeps the world in this directory between runs, it is created if it does not exist"))
+        .arg(Arg::new("settings")
+            .long("settings")
+            .value_name("FILE")
+            .default_value("settings.toml")
+            .value_parser(value_parser!(PathBuf))
+            .help("where the settings are kept, the menu behind Escape saves them"))
+        .arg(Arg::new("view-distance")
+            .long("view-distance")
+            .value_name("CHUNKS")
+            .value_parser(value_parser!(i32).range(MIN_VIEW_DISTANCE as i64..=MAX_VIEW_DISTANCE as i64))
+            .help("chunks around the spawn that are generated, measured horizontally"))
+        .arg(Arg::new("window")
+            .long("window")
+            .value_name("WxH")
+            .value_parser(parse_window)
+            .help("the size of the window in pixels"))
+        .arg(Arg::new("fly-speed")
+            .long("fly-speed")
+            .value_name("SPEED")
+            .value_parser(parse_positive)
+            .help("blocks per second while flying"))
+        .arg(Arg::new("sensitivity")
+            .long("sensitivity")
+            .value_name("SENSITIVITY")
+            .value_parser(parse_positive)
+            .help("how fast the camera turns with the mouse"))
+        .arg(Arg::new("headless")
+            .long("headless")
+            .action(ArgAction::SetTrue)
+            .conflicts_with("connect")
+            .help("runs a server without a window instead of the game"))
+        .arg(Arg::new("listen")
+            .long("listen")
+            .value_name("ADDRESS")
+            .default_value("0.0.0.0:7878")
+            .help("where the headless server listens"))
+        .arg(Arg::new("connect")
+            .long("connect")
+            .value_name("HOST:PORT")
+            .help("joins a server instead of playing alone"))
+        .arg(Arg::new("name")
+            .long("name")
+            .value_name("NAME")
+            .help("the name other players see, defaults to $USER"))
+}
+
+impl Options {
+    /// parses the arguments of the process, prints the usage and exits if they are invalid
+    pub fn parse() -> Self {
+        Self::from_matches(&command().get_matches())
+    }
+
+    fn from_matches(matches: &clap::ArgMatches) -> Self {
+        let string = |name: &str| matches.get_one::<String>(name).cloned();
+        Options {
+            seed: *matches.get_one("seed").unwrap(),
+            world: matches.get_one::<PathBuf>("world").cloned(),
+            settings: matches.get_one::<PathBuf>("settings").cloned().unwrap(),
+            view_distance: matches.get_one("view-distance").copied(),
+            window: matches.get_one("window").copied(),
+            fly_speed: matches.get_one("fly-speed").copied(),
+            sensitivity: matches.get_one("sensitivity").copied(),
+            headless: matches.get_flag("headless"),
+            listen: string("listen").unwrap(),
+            connect: string("connect"),
+            name: string("name")
+                .or_else(|| std::env::var("USER").ok())
+                .unwrap_or_else(|| "player".to_string()),
+        }
+    }
+
+    /// replaces the settings that were given on the command line
+    pub fn override_settings(&self, settings: &mut Settings) {
+        if let Some(view_distance) = self.view_distance {
+            settings.view_distance = view_distance;
+        }
+        if let Some(window) = self.window {
+            settings.window = window;
+        }
+        if let Some(fly_speed) = self.fly_speed {
+            settings.fly_speed = fly_speed;
+        }
+        if let Some(sensitivity) = self.sensitivity {
+            settings.sensitivity = sensitivity;
+        }
+    }
+}
+
+// a number above 0, which rules out nan and inf as well
+fn parse_positive(number: &str) -> Result<f32, String> {
+    let value: f32 = number.parse().map_err(|e| format!("invalid number {}: {}", number, e))?;
+    if !(value.is_finite() && value > 0.0) {
+        return Err(format!("{} is not above 0", number));
+    }
+    Ok(value)
+}
+
+// `1200x800` into width and height
+fn parse_window(size: &str) -> Result<[u32; 2], String> {
+    let (width, height) = size.split_once('x').ok_or_else(|| format!("expected WxH, got {}", size))?;
+    let width: u32 = width.parse().map_err(|e| format!("invalid width {}: {}", width, e))?;
+    let height: u32 = height.parse().map_err(|e| format!("invalid height {}: {}", height, e))?;
+    if width == 0 || height == 0 {
+        return Err(format!("the window can not be {}", size));
+    }
+    Ok([width, height])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn window_sizes() {
+        assert_eq!(parse_window("1200x800"), Ok([1200, 800]));
+        assert!(parse_window("1200").is_err());
+        assert!(parse_window("1200x").is_err());
+        assert!(parse_window("0x800").is_err());
+        assert!(parse_window("1200x0").is_err());
+    }
+
+    #[test]
+    fn servers_keep_their_own_world() {
+        assert!(command().try_get_matches_from(["voxel", "--world", "saves", "--connect", "host:7878"]).is_err());
+    }
+
+    #[test]
+    fn speeds_are_positive() {
+        assert_eq!(parse_positive("2.5"), Ok(2.5));
+        assert!(parse_positive("0").is_err());
+        assert!(parse_positive("-1").is_err());
+        assert!(parse_positive("nan").is_err());
+        assert!(parse_positive("inf").is_err());
+    }
+
+    #[test]
+    fn view_distance_is_limited() {
+        assert!(command().try_get_matches_from(["voxel", "--view-distance", "0"]).is_err());
+        assert!(command().try_get_matches_from(["voxel", "--view-distance", "17"]).is_err());
+        let matches = command().try_get_matches_from(["voxel", "--view-distance", "3"]).unwrap();
+        assert_eq!(Options::from_matches(&matches).view_distance, Some(3));
+    }
+}
@@ -0,0 +1,142 @@
+//! the connection of a game to a `server`, messages are read on a thread of their own
+
+use std::collections::HashMap;
+use std::io::{self, BufReader, BufWriter, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+use crate::block::Block;
+use crate::protocol::{self, ClientMessage, ServerMessage, VERSION};
+use crate::world::World;
+
+// how long to wait for the answer to `Hello`
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// what the server told the client when it joined
+#[derive(Clone, Copy, Debug)]
+pub struct Welcome {
+    pub player: u32,
+    pub seed: u32,
+    pub sea_level: i32,
+    pub spawn: [f32; 3],
+}
+
+pub struct Connection {
+    pub welcome: Welcome,
+    stream: BufWriter<TcpStream>,
+    // behind a mutex, so that the connection can be shared between threads
+    messages: Mutex<Receiver<ServerMessage>>,
+    closed: bool,
+}
+
+impl Connection {
+    /// connects and joins with the given name, fails if the server does not accept the client
+    pub fn connect(address: impl ToSocketAddrs, name: &str) -> io::Result<Self> {
+        let stream = TcpStream::connect(address)?;
+        stream.set_nodelay(true)?;
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let mut writer = BufWriter::new(stream);
+
+        protocol::write_client_message(&mut writer, &ClientMessage::Hello {version: VERSION, name: name.to_string()})?;
+        writer.flush()?;
+
+        writer.get_ref().set_read_timeout(Some(HANDSHAKE_TIMEOUT))?;
+        let welcome = match protocol::read_server_message(&mut reader)? {
+            ServerMessage::Welcome { player, seed, sea_level, spawn } => Welcome {player, seed, sea_level, spawn},
+            ServerMessage::Rejected { reason } => return Err(io::Error::new(io::ErrorKind::ConnectionRefused, reason)),
+            message => {
+                let error = format!("expected a welcome, got {:?}", message);
+                return Err(io::Error::new(io::ErrorKind::InvalidData, error));
+            }
+        };
+        writer.get_ref().set_read_timeout(None)?;
+
+        let (sender, messages) = mpsc::channel();
+        thread::spawn(move || {
+            while let Ok(message) = protocol::read_server_message(&mut reader) {
+                if sender.send(message).is_err() {
+                    return;
+                }
+            }
+        });
+
+        Ok(Connection {welcome, stream: writer, messages: Mutex::new(messages), closed: false})
+    }
+
+    /// queues a message, it is sent with the next `flush`
+    pub fn send(&mut self, message: &ClientMessage) {
+        if protocol::write_client_message(&mut self.stream, message).is_err() {
+            self.closed = true;
+        }
+    }
+
+    pub fn flush(&mut self) {
+        if self.stream.flush().is_err() {
+            self.closed = true;
+        }
+    }
+
+    /// the messages that arrived since the last call
+    pub fn receive(&mut self) -> Vec<ServerMessage> {
+        let messages = self.messages.get_mut().unwrap();
+        let mut received = Vec::new();
+        loop {
+            match messages.try_recv() {
+                Ok(message) => received.push(message),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    self.closed = true;
+                    break;
+                }
+            }
+        }
+        received
+    }
+
+    /// true once the server closed the connection or it broke
+    pub fn is_closed(&self) -> bool {
+        self.closed
+    }
+}
+
+/// edits that were applied to the local world before the server confirmed them,
+/// the server answers every edit with the block it ended up with
+#[derive(Default)]
+pub struct Prediction {
+    // number of edits at a position that the server did not answer yet
+    pending: HashMap<[i32; 3], usize>,
+}
+
+impl Prediction {
+    /// applies an edit locally, returns false if nothing changed and the edit does not have to be sent
+    pub fn predict(&mut self, world: &mut World, position: [i32; 3], block: Block) -> bool {
+        match world.get(position) {
+            Some(current) if current != block => {}
+            _ => return false,
+        }
+        world.set(position, block);
+        *self.pending.entry(position).or_default() += 1;
+        true
+    }
+
+    /// applies a change sent by the server, unless a newer local edit at the same position is still on its way
+    pub fn confirm(&mut self, world: &mut World, position: [i32; 3], block: Block) {
+        if let Some(pending) = self.pending.get_mut(&position) {
+            *pending -= 1;
+            if *pending > 0 {
+                return;
+            }
+            self.pending.remove(&position);
+        }
+        if world.get(position).map(|current| current != block).unwrap_or(false) {
+            world.set(position, block);
+        }
+    }
+
+    pub fn is_pending(&self, position: [i32; 3]) -> bool {
+        self.pending.contains_key(&position)
+    }
+}
@@ -0,0 +1,192 @@
+//! the text side of the developer console: the history of entered lines, tab completion and
+//! the arguments of the commands, the commands themselves are registered by the game
+
+/// the lines entered so far, Up and Down walk through them like in a shell
+#[derive(Default)]
+pub struct History {
+    lines: Vec<String>,
+    // index into `lines` while walking through them, `None` at the empty line after the newest one
+    browsing: Option<usize>,
+}
+impl History {
+    /// empty lines and repeats of the newest line are not kept
+    pub fn push(&mut self, line: &str) {
+        self.browsing = None;
+        if line.trim().is_empty() || self.lines.last().map(String::as_str) == Some(line) {
+            return;
+        }
+        self.lines.push(line.to_string());
+    }
+
+    /// an older line, stays at the oldest one
+    pub fn older(&mut self) -> Option<&str> {
+        let index = match self.browsing {
+            Some(index) => index.saturating_sub(1),
+            None => self.lines.len().checked_sub(1)?,
+        };
+        self.browsing = Some(index);
+        Some(&self.lines[index])
+    }
+
+    /// a newer line, `None` once the newest line is left
+    pub fn newer(&mut self) -> Option<&str> {
+        let index = self.browsing? + 1;
+        if index >= self.lines.len() {
+            self.browsing = None;
+            return None;
+        }
+        self.browsing = Some(index);
+        Some(&self.lines[index])
+    }
+}
+
+/// the result of pressing Tab
+#[derive(Debug, PartialEq)]
+pub struct Completion {
+    // the input with its last word completed as far as all matches agree
+    pub input: String,
+    pub matches: Vec<String>,
+}
+
+/// completes the last word of the input with the candidates that start with it,
+/// a single match is completed with a space after it
+pub fn complete(input: &str, candidates: &[String]) -> Completion {
+    let start = input.rfind(' ').map(|space| space + 1).unwrap_or(0);
+    let word = &input[start..];
+
+    let mut matches: Vec<String> = candidates.iter()
+        .filter(|candidate| candidate.starts_with(word))
+        .cloned()
+        .collect();
+    matches.sort();
+    matches.dedup();
+
+    let mut input = input.to_string();
+    if let Some(first) = matches.first() {
+        let mut prefix = first.len();
+        for other in matches.iter().skip(1) {
+            prefix = first.bytes().zip(other.bytes()).take(prefix).take_while(|(a, b)| a == b).count();
+        }
+        while !first.is_char_boundary(prefix) {
+            prefix -= 1;
+        }
+        input.truncate(start);
+        input.push_str(&first[..prefix]);
+        if matches.len() == 1 {
+            input.push(' ');
+        }
+    }
+    Completion {input, matches}
+}
+
+/// a number that is neither infinite nor `nan`, which `f32::from_str` both accepts
+pub fn parse_number(argument: &str) -> Option<f32> {
+    argument.parse::<f32>().ok().filter(|number| number.is_finite())
+}
+
+/// a coordinate like `12.5`, or relative to the origin like `~` and `~-3`
+pub fn parse_coordinate(argument: &str, origin: f32) -> Result<f32, String> {
+    let (relative, number) = match argument.strip_prefix('~') {
+        Some("") => return Ok(origin),
+        Some(number) => (true, number),
+        None => (false, argument),
+    };
+    let value = parse_number(number)
+        .map(|value| if relative { origin + value } else { value })
+        .filter(|value| value.is_finite());
+    value.ok_or_else(|| format!("{} is not a coordinate", argument))
+}
+
+/// three coordinates of a block, relative ones start at the block of the origin
+pub fn parse_position(arguments: &[&str], origin: [f32; 3]) -> Result<[i32; 3], String> {
+    if arguments.len() != 3 {
+        return Err("a position needs x, y and z".to_string());
+    }
+    let mut position = [0; 3];
+    for (coordinate, (argument, origin)) in position.iter_mut().zip(arguments.iter().zip(origin.iter())) {
+        *coordinate = parse_coordinate(argument, origin.floor())?.floor() as i32;
+    }
+    Ok(position)
+}
+
+/// the number of blocks in the box between two corners, `None` if it does not fit into a u64
+pub fn volume(from: [i32; 3], to: [i32; 3]) -> Option<u64> {
+    // the extents are computed in i64, corners at both ends of the i32 range would overflow
+    (0..3).try_fold(1u64, |volume, i| {
+        let extent = (from[i] as i64 - to[i] as i64).unsigned_abs() + 1;
+        volume.checked_mul(extent)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(words: &[&str]) -> Vec<String> {
+        words.iter().map(|word| word.to_string()).collect()
+    }
+
+    #[test]
+    fn history_walks_back_and_forth() {
+        let mut history = History::default();
+        assert_eq!(history.older(), None);
+
+        history.push("seed");
+        history.push("tp 0 60 0");
+        history.push("tp 0 60 0");
+        history.push("  ");
+
+        assert_eq!(history.older(), Some("tp 0 60 0"));
+        assert_eq!(history.older(), Some("seed"));
+        assert_eq!(history.older(), Some("seed"));
+        assert_eq!(history.newer(), Some("tp 0 60 0"));
+        assert_eq!(history.newer(), None);
+        assert_eq!(history.newer(), None);
+    }
+
+    #[test]
+    fn completes_as_far_as_the_matches_agree() {
+        let blocks = strings(&["stone", "stone_slab", "stone_stairs", "sand"]);
+
+        let completion = complete("setblock 0 0 0 sto", &blocks);
+        assert_eq!(completion.input, "setblock 0 0 0 stone");
+        assert_eq!(completion.matches, strings(&["stone", "stone_slab", "stone_stairs"]));
+
+        assert_eq!(complete("setblock 0 0 0 sa", &blocks).input, "setblock 0 0 0 sand ");
+        assert_eq!(complete("setblock 0 0 0 x", &blocks), Completion {input: "setblock 0 0 0 x".to_string(), matches: Vec::new()});
+    }
+
+    #[test]
+    fn the_first_word_completes_alone() {
+        let commands = strings(&["seed", "setblock", "speed"]);
+        assert_eq!(complete("se", &commands).input, "se");
+        assert_eq!(complete("set", &commands).input, "setblock ");
+        assert_eq!(complete("", &commands).matches.len(), 3);
+    }
+
+    #[test]
+    fn positions_can_be_relative() {
+        assert_eq!(parse_position(&["1", "~", "~-2"], [10.5, 64.9, -3.2]), Ok([1, 64, -6]));
+        assert_eq!(parse_position(&["-0.5", "~1.5", "0"], [0.0, 0.0, 0.0]), Ok([-1, 1, 0]));
+        assert!(parse_position(&["1", "2"], [0.0; 3]).is_err());
+        assert!(parse_position(&["1", "2", "up"], [0.0; 3]).is_err());
+    }
+
+    #[test]
+    fn numbers_are_finite() {
+        assert_eq!(parse_number("-2.5"), Some(-2.5));
+        assert_eq!(parse_number("nan"), None);
+        assert_eq!(parse_number("inf"), None);
+        assert!(parse_coordinate("NaN", 0.0).is_err());
+        assert!(parse_coordinate("~-infinity", 0.0).is_err());
+        assert!(parse_coordinate("~3e38", 3e38).is_err());
+    }
+
+    #[test]
+    fn volumes_do_not_overflow() {
+        assert_eq!(volume([0, 0, 0], [0, 0, 0]), Some(1));
+        assert_eq!(volume([2, -1, 5], [-1, 1, 5]), Some(12));
+        assert_eq!(volume([i32::MIN, 0, 0], [i32::MAX, 0, 0]), Some(1 << 32));
+        assert_eq!(volume([i32::MIN; 3], [i32::MAX; 3]), None);
+    }
+}
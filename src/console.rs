@@ -0,0 +1,455 @@
+use std::collections::{BTreeMap, VecDeque};
+
+use bevy::ecs::world::World as EcsWorld;
+use bevy::prelude::*;
+use bevy::window::ReceivedCharacter;
+
+use voxel::block::{self, Block, BlockId};
+use voxel::command_line::{self, History};
+use voxel::coords::{BlockPos, ChunkPos};
+use voxel::custom_model::CustomModels;
+use voxel::settings::Settings;
+use voxel::world::{self as terrain, Chunk, World};
+
+use crate::daytime::TimeOfDay;
+use crate::menu::Menu;
+use crate::network::Network;
+use crate::player_input::{FlyCam, MovementSettings};
+
+// lines of output that stay on the screen
+const OUTPUT_LINES: usize = 12;
+// the most blocks `fill` replaces at once, the size of a chunk
+const MAX_FILL: usize = 32 * 32 * 32;
+
+/// runs a command with the words after its name, the text is printed to the console
+pub type CommandFn = fn(&[&str], &mut EcsWorld) -> Result<String, String>;
+/// what Tab completes after the name of a command
+pub type WordsFn = fn(&EcsWorld) -> Vec<String>;
+
+#[derive(Clone, Copy)]
+pub struct ConsoleCommand {
+    // shown by `help` and after errors
+    pub usage: &'static str,
+    pub run: CommandFn,
+    pub words: WordsFn,
+}
+impl ConsoleCommand {
+    pub fn new(usage: &'static str, run: CommandFn) -> Self {
+        ConsoleCommand {usage, run, words: |_| Vec::new()}
+    }
+
+    pub fn with_words(self, words: WordsFn) -> Self {
+        ConsoleCommand {words, ..self}
+    }
+}
+
+/// the commands of the console by their names, see `AddConsoleCommand`
+#[derive(Default)]
+pub struct ConsoleCommands {
+    commands: BTreeMap<String, ConsoleCommand>,
+}
+
+/// lets other systems add their own commands to the console
+pub trait AddConsoleCommand {
+    fn add_console_command(&mut self, name: &str, command: ConsoleCommand) -> &mut Self;
+}
+impl AddConsoleCommand for AppBuilder {
+    fn add_console_command(&mut self, name: &str, command: ConsoleCommand) -> &mut Self {
+        self.world_mut()
+            .get_resource_or_insert_with(ConsoleCommands::default)
+            .commands
+            .insert(name.to_string(), command);
+        self
+    }
+}
+
+// what the keyboard asked for, carried out by `run_console` which has the whole world
+enum Request {
+    Run(String),
+    Complete,
+}
+
+#[derive(Default)]
+pub struct Console {
+    pub open: bool,
+    input: String,
+    history: History,
+    output: VecDeque<String>,
+    requests: Vec<Request>,
+}
+impl Console {
+    pub fn print(&mut self, text: &str) {
+        for line in text.lines() {
+            self.output.push_back(line.to_string());
+        }
+        while self.output.len() > OUTPUT_LINES {
+            self.output.pop_front();
+        }
+    }
+}
+
+pub struct ConsoleText;
+
+/// the console behind the ` key with the built in commands
+pub struct ConsolePlugin;
+impl Plugin for ConsolePlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<Console>()
+            .init_resource::<ConsoleCommands>()
+            .add_startup_system(setup_console.system())
+            .add_system(console_input.system().label("console").after("menu"))
+            .add_system(update_console_text.system().after("console"))
+            .add_system(run_console.exclusive_system().at_end())
+
+            .add_console_command("help", ConsoleCommand::new("help", help))
+            .add_console_command("tp", ConsoleCommand::new("tp x y z, ~ is relative to the camera", teleport))
+            .add_console_command("seed", ConsoleCommand::new("seed", seed))
+            .add_console_command("setblock", ConsoleCommand::new("setblock x y z <block>", set_block).with_words(block_names))
+            .add_console_command("fill", ConsoleCommand::new("fill x1 y1 z1 x2 y2 z2 <block>", fill).with_words(block_names))
+            .add_console_command("regen", ConsoleCommand::new("regen chunk [x y z], the chunk of the camera without a position", regenerate)
+                .with_words(|_| vec!["chunk".to_string()]))
+            .add_console_command("time", ConsoleCommand::new("time [set <hour|day|noon|night|midnight>]", time)
+                .with_words(|_| ["set", "day", "noon", "night", "midnight"].iter().map(|word| word.to_string()).collect()))
+            .add_console_command("speed", ConsoleCommand::new("speed [blocks per second]", speed));
+    }
+}
+
+fn setup_console(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands
+        .spawn_bundle(TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {left: Val::Px(20.0), bottom: Val::Px(20.0), ..Default::default()},
+                ..Default::default()
+            },
+            text: Text::with_section(
+                String::new(),
+                TextStyle {font: asset_server.load("fonts/DejaVuSansMono.ttf"), font_size: 18.0, color: Color::WHITE},
+                Default::default(),
+            ),
+            visible: Visible {is_visible: false, is_transparent: true},
+            ..Default::default()
+        })
+        .insert(ConsoleText);
+}
+
+/// ` opens and closes the console, Escape closes it, Up and Down go through the history and Tab completes
+pub fn console_input(
+    keys: Res<Input<KeyCode>>,
+    mut characters: EventReader<ReceivedCharacter>,
+    menu: Res<Menu>,
+    mut console: ResMut<Console>,
+    mut windows: ResMut<Windows>,
+) {
+    // the ` that opened the console is typed as well
+    let typed: String = characters.iter()
+        .map(|character| character.char)
+        .filter(|character| !character.is_control() && *character != '`')
+        .collect();
+
+    let toggle = keys.just_pressed(KeyCode::Grave) || (console.open && keys.just_pressed(KeyCode::Escape));
+    if toggle && !menu.open {
+        console.open = !console.open;
+        let window = windows.get_primary_mut().unwrap();
+        window.set_cursor_lock_mode(!console.open);
+        window.set_cursor_visibility(console.open);
+        return;
+    }
+    if !console.open {
+        return;
+    }
+
+    let console = &mut *console;
+    if keys.just_pressed(KeyCode::Return) {
+        let line = std::mem::take(&mut console.input);
+        console.history.push(&line);
+        console.print(&format!("> {}", line));
+        console.requests.push(Request::Run(line));
+    }
+    if keys.just_pressed(KeyCode::Back) {
+        console.input.pop();
+    }
+    if keys.just_pressed(KeyCode::Up) {
+        if let Some(line) = console.history.older() {
+            console.input = line.to_string();
+        }
+    }
+    if keys.just_pressed(KeyCode::Down) {
+        console.input = console.history.newer().unwrap_or_default().to_string();
+    }
+    if keys.just_pressed(KeyCode::Tab) {
+        console.requests.push(Request::Complete);
+    }
+    console.input.push_str(&typed);
+}
+
+fn update_console_text(
+    console: Res<Console>,
+    mut text: Query<(&mut Text, &mut Visible), With<ConsoleText>>,
+) {
+    if !console.is_changed() {
+        return;
+    }
+    for (mut text, mut visible) in text.iter_mut() {
+        visible.is_visible = console.open;
+        let mut value: String = console.output.iter().map(|line| format!("{}\n", line)).collect();
+        value.push_str(&format!("> {}_", console.input));
+        text.sections[0].value = value;
+    }
+}
+
+/// carries out the entered commands, they get the whole world so that they can change anything
+fn run_console(world: &mut EcsWorld) {
+    let requests = match world.get_resource_mut::<Console>() {
+        Some(mut console) if !console.requests.is_empty() => std::mem::take(&mut console.requests),
+        _ => return,
+    };
+
+    for request in requests {
+        match request {
+            Request::Run(line) => {
+                let words: Vec<&str> = line.split_whitespace().collect();
+                let name = match words.first() {
+                    Some(name) => *name,
+                    None => continue,
+                };
+                let command = world.get_resource::<ConsoleCommands>().and_then(|commands| commands.commands.get(name).copied());
+                let output = match command {
+                    Some(command) => match (command.run)(&words[1..], world) {
+                        Ok(output) => output,
+                        Err(e) => format!("{}\nusage: {}", e, command.usage),
+                    },
+                    None => format!("unknown command {}, try help", name),
+                };
+                world.get_resource_mut::<Console>().unwrap().print(&output);
+            }
+            Request::Complete => {
+                let input = world.get_resource::<Console>().unwrap().input.clone();
+                let candidates = {
+                    let commands = world.get_resource::<ConsoleCommands>().unwrap();
+                    match input.trim_start().split_once(' ') {
+                        // the name of the command is not complete yet
+                        None => commands.commands.keys().cloned().collect(),
+                        Some((name, _)) => commands.commands.get(name).map(|command| (command.words)(world)).unwrap_or_default(),
+                    }
+                };
+                let completion = command_line::complete(&input, &candidates);
+
+                let mut console = world.get_resource_mut::<Console>().unwrap();
+                if completion.matches.len() > 1 {
+                    console.print(&completion.matches.join("  "));
+                }
+                console.input = completion.input;
+            }
+        }
+    }
+}
+
+fn camera_position(world: &mut EcsWorld) -> [f32; 3] {
+    let mut camera = world.query_filtered::<&Transform, With<FlyCam>>();
+    camera.iter(world)
+        .next()
+        .map(|camera| [camera.translation.x, camera.translation.y, camera.translation.z])
+        .unwrap_or([0.0; 3])
+}
+
+// built in blocks, the blocks of `assets/models/blocks.json` or plain ids
+fn parse_block(name: &str, world: &EcsWorld) -> Result<BlockId, String> {
+    if let Some(block) = block::from_name(name) {
+        return Ok(block);
+    }
+    let models = world.get_resource::<CustomModels>();
+    if let Some(index) = models.and_then(|models| models.models.iter().position(|model| model.name == name)) {
+        return Ok(block::FIRST_CUSTOM + index as BlockId);
+    }
+    let custom_blocks = models.map(|models| models.models.len()).unwrap_or(0);
+    match name.parse::<BlockId>() {
+        Ok(block) if voxel::server::is_known_block(block, custom_blocks) => Ok(block),
+        _ => Err(format!("{} is not a block", name)),
+    }
+}
+
+fn block_names(world: &EcsWorld) -> Vec<String> {
+    let custom = world.get_resource::<CustomModels>()
+        .map(|models| models.models.iter().map(|model| model.name.clone()).collect())
+        .unwrap_or_else(Vec::new);
+    block::NAMES.iter().map(|name| name.to_string()).chain(custom).collect()
+}
+
+// the blocks `set_blocks` changed, in multiplayer the server still has to accept the edits that were sent
+enum Edits {
+    Replaced(usize),
+    Sent(usize),
+}
+
+// replaces blocks like `controll::build`, in multiplayer the server checks the edits
+fn set_blocks(world: &mut EcsWorld, positions: &[[i32; 3]], block: Block) -> Edits {
+    let mut network = world.remove_resource::<Network>();
+    let mut voxels = world.query::<&mut World>();
+    let mut edited = 0;
+    for mut voxels in voxels.iter_mut(world) {
+        for position in positions {
+            edited += match network.as_mut() {
+                Some(network) => network.edit(&mut voxels, *position, block),
+                None => voxels.set(*position, block),
+            } as usize;
+        }
+    }
+    match network {
+        Some(network) => {
+            world.insert_resource(network);
+            Edits::Sent(edited)
+        }
+        None => Edits::Replaced(edited),
+    }
+}
+
+fn help(_: &[&str], world: &mut EcsWorld) -> Result<String, String> {
+    let commands = world.get_resource::<ConsoleCommands>().unwrap();
+    Ok(commands.commands.values().map(|command| command.usage).collect::<Vec<&str>>().join("\n"))
+}
+
+fn teleport(arguments: &[&str], world: &mut EcsWorld) -> Result<String, String> {
+    if arguments.len() != 3 {
+        return Err("tp needs x, y and z".to_string());
+    }
+    let origin = camera_position(world);
+    let mut target = [0.0; 3];
+    for (coordinate, (argument, origin)) in target.iter_mut().zip(arguments.iter().zip(origin.iter())) {
+        *coordinate = command_line::parse_coordinate(argument, *origin)?;
+    }
+
+    let mut camera = world.query_filtered::<&mut Transform, With<FlyCam>>();
+    for mut camera in camera.iter_mut(world) {
+        camera.translation = Vec3::new(target[0], target[1], target[2]);
+    }
+    Ok(format!("teleported to {:.1} {:.1} {:.1}", target[0], target[1], target[2]))
+}
+
+fn seed(_: &[&str], world: &mut EcsWorld) -> Result<String, String> {
+    let mut voxels = world.query::<&World>();
+    voxels.iter(world)
+        .next()
+        .map(|voxels| format!("seed {}", voxels.seed))
+        .ok_or_else(|| "there is no world yet".to_string())
+}
+
+fn set_block(arguments: &[&str], world: &mut EcsWorld) -> Result<String, String> {
+    if arguments.len() != 4 {
+        return Err("setblock needs a position and a block".to_string());
+    }
+    let position = command_line::parse_position(&arguments[..3], camera_position(world))?;
+    let block = parse_block(arguments[3], world)?;
+
+    match set_blocks(world, &[position], Block::new(block)) {
+        Edits::Replaced(0) => Err(format!("the chunk of {:?} is not loaded", position)),
+        Edits::Replaced(_) => Ok(format!("placed {} at {:?}", arguments[3], position)),
+        Edits::Sent(0) => Err(format!("{:?} is not loaded or already {}", position, arguments[3])),
+        Edits::Sent(_) => Ok(format!("sent {} at {:?} to the server", arguments[3], position)),
+    }
+}
+
+fn fill(arguments: &[&str], world: &mut EcsWorld) -> Result<String, String> {
+    if arguments.len() != 7 {
+        return Err("fill needs two corners and a block".to_string());
+    }
+    let origin = camera_position(world);
+    let from = command_line::parse_position(&arguments[..3], origin)?;
+    let to = command_line::parse_position(&arguments[3..6], origin)?;
+    let block = parse_block(arguments[6], world)?;
+
+    let size = match command_line::volume(from, to) {
+        Some(size) if size <= MAX_FILL as u64 => size as usize,
+        Some(size) => return Err(format!("{} blocks are too many, at most {} can be filled at once", size, MAX_FILL)),
+        None => return Err(format!("too many blocks, at most {} can be filled at once", MAX_FILL)),
+    };
+
+    let min = [from[0].min(to[0]), from[1].min(to[1]), from[2].min(to[2])];
+    let max = [from[0].max(to[0]), from[1].max(to[1]), from[2].max(to[2])];
+
+    let mut positions = Vec::with_capacity(size);
+    for x in min[0]..=max[0] {
+        for y in min[1]..=max[1] {
+            for z in min[2]..=max[2] {
+                positions.push([x, y, z]);
+            }
+        }
+    }
+    Ok(match set_blocks(world, &positions, Block::new(block)) {
+        Edits::Replaced(replaced) => format!("replaced {} blocks with {}", replaced, arguments[6]),
+        Edits::Sent(sent) => format!("sent {} edits to {} to the server", sent, arguments[6]),
+    })
+}
+
+fn regenerate(arguments: &[&str], world: &mut EcsWorld) -> Result<String, String> {
+    if arguments.first() != Some(&"chunk") || (arguments.len() != 1 && arguments.len() != 4) {
+        return Err("regen needs chunk and an optional chunk position".to_string());
+    }
+    if world.get_resource::<Network>().is_some() {
+        return Err("the server decides about the terrain".to_string());
+    }
+    let position: [i32; 3] = if arguments.len() == 4 {
+        let mut position = [0; 3];
+        for (coordinate, argument) in position.iter_mut().zip(arguments[1..].iter()) {
+            *coordinate = argument.parse().map_err(|_| format!("{} is not a chunk coordinate", argument))?;
+        }
+        position
+    } else {
+        BlockPos::from_point(camera_position(world)).chunk().into()
+    };
+
+    let mut voxels = world.query::<&mut World>();
+    for mut voxels in voxels.iter_mut(world) {
+        let mut generated = Chunk::new(position[0], position[1], position[2]);
+        terrain::generate_terrain(&mut generated, voxels.seed, voxels.sea_level);
+
+        match voxels.get_chunk_mut(position) {
+            Some(chunk) => chunk.blocks = generated.blocks,
+            None => return Err(format!("the chunk at {:?} is not loaded", position)),
+        }
+        crate::chunk::relight(&mut voxels, &[position]);
+
+        // a change inside of the chunk, so that `chunk::save_chunks` writes it again
+        voxels.changes.push(ChunkPos::from(position).origin().into());
+    }
+    Ok(format!("regenerated the chunk at {:?}", position))
+}
+
+fn time(arguments: &[&str], world: &mut EcsWorld) -> Result<String, String> {
+    let mut time_of_day = world.get_resource_mut::<TimeOfDay>().unwrap();
+    match arguments {
+        [] => {}
+        ["set", time] => {
+            time_of_day.time = match *time {
+                "day" => 0.3,
+                "noon" => 0.5,
+                "night" => 0.8,
+                "midnight" => 0.0,
+                hour => {
+                    let hour = command_line::parse_number(hour).ok_or_else(|| format!("{} is not an hour", hour))?;
+                    (hour / 24.0).rem_euclid(1.0)
+                }
+            };
+        }
+        _ => return Err("time only knows set".to_string()),
+    }
+    let hours = time_of_day.time * 24.0;
+    Ok(format!("it is {:02}:{:02}", hours as u32, (hours.fract() * 60.0) as u32))
+}
+
+fn speed(arguments: &[&str], world: &mut EcsWorld) -> Result<String, String> {
+    match arguments {
+        [] => {}
+        [speed] => {
+            let speed = command_line::parse_number(speed).ok_or_else(|| format!("{} is not a speed", speed))?;
+            if !(speed.is_finite() && speed > 0.0) {
+                return Err("the speed has to be above 0".to_string());
+            }
+            world.get_resource_mut::<MovementSettings>().unwrap().speed = speed;
+            // kept by the settings menu
+            world.get_resource_mut::<Settings>().unwrap().fly_speed = speed;
+        }
+        _ => return Err("speed takes a single number".to_string()),
+    }
+    Ok(format!("flying at {} blocks per second", world.get_resource::<MovementSettings>().unwrap().speed))
+}
@@ -5,59 +5,48 @@ pub struct Builder {
     y: f32,
     z: f32,
     distance: f32,
+    // block that gets placed
+    block: voxel::block::BlockId,
+    // where the camera looks, decides the orientation of placed blocks
+    direction: [f32; 3],
 }
 impl Builder {
     pub fn new(x: f32, y: f32, z: f32) -> Self {
-        Builder {x: x, y: y, z: z, distance: 5.0}
-    }
-
-    pub fn chpos(&mut self, direction: [f32; 3]) {
-        self.x += direction[0];
-        self.y += direction[1];
-        self.z += direction[2];
+        Builder {x, y, z, distance: 5.0, block: voxel::block::STONE, direction: [0.0, 0.0, -1.0]}
     }
 
     pub fn get_position(&self) -> [f32; 3] {
         [self.x, self.y ,self.z]
     }
+
+    /// the block that gets replaced, rounds down so that negative positions do not end up in the block next to the builder
+    pub fn target(&self) -> [i32; 3] {
+        voxel::coords::BlockPos::from_point([self.x - 1.0, self.y - 0.25, self.z - 1.0]).into()
+    }
 }
 
 pub struct BuilderIndicator;
 
 pub fn build(
-    mut world: Query<&mut crate::chunk::World, With<crate::chunk::World>>,
+    mut world: Query<&mut voxel::world::World, With<voxel::world::World>>,
     builder: Query<&Builder, With<Builder>>,
     mut builder_indicator: Query<&mut Transform, With<BuilderIndicator>>,
-    mut chunk_mesh: Query<(Entity, &mut crate::chunk::ChunkMesh), With<crate::chunk::ChunkMesh>>,
     input: Res<Input<MouseButton>>,
-    mut commands: Commands,
-    materials: Res<crate::Materials>,
-    mut meshes: ResMut<Assets<Mesh>>,
+    windows: Res<Windows>,
+    mut network: Option<ResMut<crate::network::Network>>,
 ) {
     let mut builder_position: [i32; 3] = [0, 0, 0];
     let mut builder_raw_position: [f32; 3] = [0.0, 0.0, 0.0];
+    let mut builder_block = voxel::block::Block::new(voxel::block::STONE);
 
     for builder in builder.iter() {
         let old_pos = builder.get_position();
-        builder_position = [(old_pos[0] - 1.0) as i32, (old_pos[1] - 0.25) as i32, (old_pos[2] - 1.0) as i32];
+        builder_position = builder.target();
 
         // updates position
         builder_raw_position = [old_pos[0], old_pos[1], old_pos[2]];
-    }
-
-    let builder_chunk_position: [i32; 3] = crate::chunk::get_chunk_coordinates_from_position(builder_position);
-
-    let mut chunk_position: usize = 0;
-
-    for mut world in world.iter_mut() {
-        chunk_position = 
-            crate::chunk::get_chunk_index( builder_chunk_position, &mut world ); 
-    }
-
-    let mut blocks: [usize; 3] = [0, 0, 0];
-
-    for i in 0..3 {
-        blocks[i] = ( (builder_position[i]) - builder_chunk_position[i] *32 ) as usize
+        builder_block.id = builder.block;
+        builder_block.state = voxel::model::placement_state(builder.block, builder.direction);
     }
 
     for mut builder_indicator in builder_indicator.iter_mut() {
@@ -67,73 +56,69 @@ pub fn build(
     }
 
 
-    let mut edited: bool = false;
+    // clicks in the menu and the console do not build
+    if !windows.get_primary().unwrap().cursor_locked() {
+        return;
+    }
 
-    // places block
+    // places block, the ChunkMesh gets replaced by `chunk::remesh_chunks`
+    let mut edit = None;
     if input.pressed(MouseButton::Right) {
-        for mut world in world.iter_mut() {
-
-            // places block in chunk index
-            world
-                .chunk_index[chunk_position]
-                .index[blocks[0]][blocks[1]][blocks[2]] = 3;
-        }
-        edited = true;
+        edit = Some(builder_block);
     }
 
     // destroys block
     if input.pressed(MouseButton::Left) {
-        for mut world in world.iter_mut() {
-
-            // places block in chunk index         
-            world
-                .chunk_index[chunk_position]
-                .index[blocks[0]][blocks[1]][blocks[2]] = 0;
-            
-        }
-        edited = true;
+        edit = Some(voxel::block::Block::new(voxel::block::AIR));
     }
 
-    // replaces ChunkMesh
-    if edited {
-        for (entity, chunk_mesh) in chunk_mesh.iter_mut() {
-            if chunk_mesh.x == builder_chunk_position[0]
-            && chunk_mesh.y == builder_chunk_position[1]
-            && chunk_mesh.z == builder_chunk_position[2]
-            {
-                commands.entity(entity).despawn();
-
-                for mut world in world.iter_mut() {
-                    let mesh = 
-                        crate::chunk::create_chunk_mesh(
-                            crate::chunk::get_chunk_index(builder_chunk_position, &mut world),
-                            &mut world
-                        );
-
-                    commands
-                        .spawn_bundle(PbrBundle {
-                            mesh: meshes.add(mesh),
-                            material: materials.blocks.clone(),
-                            transform: Transform::from_matrix(Mat4::from_scale_rotation_translation(
-                                Vec3::splat(1.0),
-                                Quat::from_rotation_x(0.0),
-                                Vec3::new((builder_chunk_position[0] * 32) as f32, (builder_chunk_position[1] * 32) as f32, (builder_chunk_position[2] * 32) as f32),
-                            )),
-                            ..Default::default()
-                        })
-                        .insert(crate::chunk::ChunkMesh::new(builder_chunk_position[0], builder_chunk_position[1], builder_chunk_position[2]));
+    if let Some(block) = edit {
+        for mut world in world.iter_mut() {
+            // in multiplayer the server has the last word, see `network::Network::edit`
+            match network.as_mut() {
+                Some(network) => {
+                    network.edit(&mut world, builder_position, block);
+                }
+                None => {
+                    world.set(builder_position, block);
                 }
             }
-        } 
+        }
     }
-    
 }
 
+// blocks that can be selected with the number keys and Z, X, C, V, N, M and comma
+const HOTBAR: [(KeyCode, voxel::block::BlockId); 17] = [
+    (KeyCode::Key1, voxel::block::STONE),
+    (KeyCode::Key2, voxel::block::DIRT),
+    (KeyCode::Key3, voxel::block::GRASS),
+    (KeyCode::Key4, voxel::block::SAND),
+    (KeyCode::Key5, voxel::block::CRIMSON_STONE),
+    (KeyCode::Key6, voxel::block::MAGMA),
+    (KeyCode::Key7, voxel::block::WATER),
+    (KeyCode::Key8, voxel::block::LAVA),
+    (KeyCode::Key9, voxel::block::GRAVEL),
+    (KeyCode::Key0, voxel::block::TORCH),
+    (KeyCode::Z, voxel::block::STONE_SLAB),
+    (KeyCode::X, voxel::block::STONE_STAIRS),
+    (KeyCode::C, voxel::block::FENCE),
+    (KeyCode::V, voxel::block::FLOWER),
+    (KeyCode::N, voxel::block::GLASS),
+    (KeyCode::M, voxel::block::LEAVES),
+    (KeyCode::Comma, voxel::block::PORTAL),
+];
+
 
 pub fn builder_movement(
     input: Res<Input<KeyCode>>,
+    models: Res<voxel::custom_model::CustomModels>,
+    windows: Res<Windows>,
     mut builder: Query<&mut Builder, With<Builder>>,
 ) {
+    // the hotbar keys are typed into the menu and the console while the cursor is free
+    if !windows.get_primary().unwrap().cursor_locked() {
+        return;
+    }
     for mut builder in builder.iter_mut() {
         if input.just_pressed(KeyCode::Up) {
             builder.distance += 1.0;
@@ -141,6 +126,20 @@ pub fn builder_movement(
         if input.just_pressed(KeyCode::Down) {
             builder.distance -= 1.0;
         }
+        for (key, block) in HOTBAR.iter() {
+            if input.just_pressed(*key) {
+                builder.block = *block;
+            }
+        }
+        // cycles through the blocks loaded from the assets
+        if input.just_pressed(KeyCode::B) && !models.models.is_empty() {
+            let next = builder.block.saturating_sub(voxel::block::FIRST_CUSTOM) + 1;
+            builder.block = if builder.block < voxel::block::FIRST_CUSTOM || next as usize >= models.models.len() {
+                voxel::block::FIRST_CUSTOM
+            } else {
+                voxel::block::FIRST_CUSTOM + next
+            };
+        }
     }
 }
 
@@ -156,6 +155,7 @@ pub fn movement(
             builder.x = camera.translation.x + forward[0] * builder.distance;
             builder.y = camera.translation.y + forward[1] * builder.distance;
             builder.z = camera.translation.z + forward[2] * builder.distance;
+            builder.direction = [forward[0], forward[1], forward[2]];
         }
     }
 }
\ No newline at end of file
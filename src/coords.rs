@@ -0,0 +1,186 @@
+// blocks along every axis of a chunk
+pub const CHUNK_SIZE: i32 = 32;
+
+/// position of a block in the world
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct BlockPos {
+    pub x: i32,
+    pub y: i32,
+    pub z: i32,
+}
+impl BlockPos {
+    pub fn new(x: i32, y: i32, z: i32) -> Self {
+        BlockPos {x, y, z}
+    }
+
+    /// the block a point in world space lies in, a block covers its position up to the next one
+    pub fn from_point(point: [f32; 3]) -> Self {
+        BlockPos::new(point[0].floor() as i32, point[1].floor() as i32, point[2].floor() as i32)
+    }
+
+    /// the chunk containing the block, also for negative positions
+    pub fn chunk(self) -> ChunkPos {
+        ChunkPos::new(
+            self.x.div_euclid(CHUNK_SIZE),
+            self.y.div_euclid(CHUNK_SIZE),
+            self.z.div_euclid(CHUNK_SIZE),
+        )
+    }
+
+    /// the position of the block inside of its chunk
+    pub fn local(self) -> LocalPos {
+        LocalPos {
+            x: self.x.rem_euclid(CHUNK_SIZE) as usize,
+            y: self.y.rem_euclid(CHUNK_SIZE) as usize,
+            z: self.z.rem_euclid(CHUNK_SIZE) as usize,
+        }
+    }
+
+    pub fn split(self) -> (ChunkPos, LocalPos) {
+        (self.chunk(), self.local())
+    }
+}
+impl From<[i32; 3]> for BlockPos {
+    fn from(position: [i32; 3]) -> Self {
+        BlockPos::new(position[0], position[1], position[2])
+    }
+}
+impl From<BlockPos> for [i32; 3] {
+    fn from(position: BlockPos) -> Self {
+        [position.x, position.y, position.z]
+    }
+}
+
+/// position of a chunk in chunks, the chunk at `[1, 0, 0]` starts at block `[32, 0, 0]`
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct ChunkPos {
+    pub x: i32,
+    pub y: i32,
+    pub z: i32,
+}
+impl ChunkPos {
+    pub fn new(x: i32, y: i32, z: i32) -> Self {
+        ChunkPos {x, y, z}
+    }
+
+    /// the block of the chunk with the lowest coordinates
+    pub fn origin(self) -> BlockPos {
+        BlockPos::new(self.x * CHUNK_SIZE, self.y * CHUNK_SIZE, self.z * CHUNK_SIZE)
+    }
+
+    /// the world position of a block inside of the chunk
+    pub fn block(self, local: LocalPos) -> BlockPos {
+        let origin = self.origin();
+        BlockPos::new(origin.x + local.x as i32, origin.y + local.y as i32, origin.z + local.z as i32)
+    }
+}
+impl From<[i32; 3]> for ChunkPos {
+    fn from(position: [i32; 3]) -> Self {
+        ChunkPos::new(position[0], position[1], position[2])
+    }
+}
+impl From<ChunkPos> for [i32; 3] {
+    fn from(position: ChunkPos) -> Self {
+        [position.x, position.y, position.z]
+    }
+}
+
+/// position of a block inside of a chunk, every axis is in `0..CHUNK_SIZE`
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct LocalPos {
+    pub x: usize,
+    pub y: usize,
+    pub z: usize,
+}
+impl LocalPos {
+    /// `None` if the position lies outside of the chunk
+    pub fn new(x: usize, y: usize, z: usize) -> Option<Self> {
+        let size = CHUNK_SIZE as usize;
+        if x < size && y < size && z < size {
+            Some(LocalPos {x, y, z})
+        } else {
+            None
+        }
+    }
+}
+impl From<LocalPos> for [usize; 3] {
+    fn from(position: LocalPos) -> Self {
+        [position.x, position.y, position.z]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    #[test]
+    fn negative_boundaries() {
+        assert_eq!(BlockPos::new(-1, -32, -33).chunk(), ChunkPos::new(-1, -1, -2));
+        assert_eq!(BlockPos::new(-1, -32, -33).local(), LocalPos::new(31, 0, 31).unwrap());
+        assert_eq!(BlockPos::new(0, 31, 32).chunk(), ChunkPos::new(0, 0, 1));
+        assert_eq!(BlockPos::new(0, 31, 32).local(), LocalPos::new(0, 31, 0).unwrap());
+    }
+
+    #[test]
+    fn points_round_down() {
+        assert_eq!(BlockPos::from_point([-0.5, 0.5, -1.0]), BlockPos::new(-1, 0, -1));
+        assert_eq!(BlockPos::from_point([-32.25, 31.75, 0.0]).chunk(), ChunkPos::new(-2, 0, 0));
+    }
+
+    #[test]
+    fn local_positions_stay_inside_of_the_chunk() {
+        assert!(LocalPos::new(31, 31, 31).is_some());
+        assert!(LocalPos::new(32, 0, 0).is_none());
+        assert!(LocalPos::new(0, 0, usize::MAX).is_none());
+    }
+
+    // keeps `origin` and `block` from overflowing
+    fn block_coordinate() -> impl Strategy<Value = i32> {
+        prop_oneof![
+            -1_000_000..1_000_000,
+            // the borders of the chunks around the origin
+            (-4..4).prop_flat_map(|chunk: i32| (chunk * CHUNK_SIZE - 1)..=(chunk * CHUNK_SIZE)),
+        ]
+    }
+
+    proptest! {
+        #[test]
+        fn split_round_trips(x in block_coordinate(), y in block_coordinate(), z in block_coordinate()) {
+            let position = BlockPos::new(x, y, z);
+            let (chunk, local) = position.split();
+            prop_assert_eq!(chunk.block(local), position);
+        }
+
+        #[test]
+        fn local_is_inside_of_the_chunk(x in any::<i32>(), y in any::<i32>(), z in any::<i32>()) {
+            let local = BlockPos::new(x, y, z).local();
+            prop_assert_eq!(LocalPos::new(local.x, local.y, local.z), Some(local));
+        }
+
+        #[test]
+        fn blocks_of_a_chunk_belong_to_it(
+            chunk in (-30_000..30_000, -30_000..30_000, -30_000..30_000),
+            local in (0..32usize, 0..32usize, 0..32usize),
+        ) {
+            let chunk = ChunkPos::new(chunk.0, chunk.1, chunk.2);
+            let local = LocalPos::new(local.0, local.1, local.2).unwrap();
+            prop_assert_eq!(chunk.block(local).split(), (chunk, local));
+        }
+
+        #[test]
+        fn neighbouring_blocks_are_in_the_same_or_the_next_chunk(x in block_coordinate()) {
+            let (a, b) = (BlockPos::new(x, 0, 0).chunk(), BlockPos::new(x + 1, 0, 0).chunk());
+            prop_assert!(b.x == a.x || b.x == a.x + 1);
+            prop_assert_eq!(b.x == a.x + 1, BlockPos::new(x + 1, 0, 0).local().x == 0);
+        }
+
+        #[test]
+        fn points_lie_in_their_block(x in -1.0e5f32..1.0e5, y in -1.0e5f32..1.0e5, z in -1.0e5f32..1.0e5) {
+            let block = BlockPos::from_point([x, y, z]);
+            for (point, min) in [(x, block.x), (y, block.y), (z, block.z)].iter() {
+                prop_assert!(*min as f32 <= *point && *point < *min as f32 + 1.0);
+            }
+        }
+    }
+}
@@ -0,0 +1,75 @@
+use crate::block;
+use crate::mesh::FACES;
+use crate::world::Chunk;
+
+/// which faces of a chunk can see each other through the blocks that are not opaque,
+/// bit `a * 6 + b` is set when face `a` and `b` of `FACES` are connected
+pub fn connectivity(chunk: &Chunk) -> u64 {
+    // a chunk of a single block is either completely open or completely closed
+    if let Some(block) = chunk.blocks.uniform() {
+        return if block::is_opaque(block.id) { 0 } else { u64::MAX };
+    }
+
+    let mut visited = vec![false; 32 * 32 * 32];
+    let mut connections: u64 = 0;
+
+    for start in 0..32 * 32 * 32 {
+        let [x, y, z] = [start / 1024, start / 32 % 32, start % 32];
+        if visited[start] || block::is_opaque(chunk.get([x, y, z]).id) {
+            continue;
+        }
+
+        // flood fills a region of connected blocks and collects the faces it touches
+        let mut faces: u8 = 0;
+        let mut stack: Vec<[usize; 3]> = vec![[x, y, z]];
+        visited[start] = true;
+
+        while let Some(position) = stack.pop() {
+            faces |= touched_faces(position);
+
+            for face in FACES.iter() {
+                let neighbour = [
+                    position[0] as i32 + face.normal[0],
+                    position[1] as i32 + face.normal[1],
+                    position[2] as i32 + face.normal[2],
+                ];
+                if neighbour.iter().any(|axis| !(0..32).contains(axis)) {
+                    continue;
+                }
+
+                let [nx, ny, nz] = [neighbour[0] as usize, neighbour[1] as usize, neighbour[2] as usize];
+                let index = nx * 1024 + ny * 32 + nz;
+                if !visited[index] && !block::is_opaque(chunk.get([nx, ny, nz]).id) {
+                    visited[index] = true;
+                    stack.push([nx, ny, nz]);
+                }
+            }
+        }
+
+        for a in 0..6 {
+            for b in 0..6 {
+                if faces & (1 << a) != 0 && faces & (1 << b) != 0 {
+                    connections |= 1 << (a * 6 + b);
+                }
+            }
+        }
+    }
+
+    connections
+}
+
+// faces of the chunk the position lies on, as bits in the order of `FACES`
+fn touched_faces(position: [usize; 3]) -> u8 {
+    let mut faces = 0;
+    if position[1] == 0 { faces |= 1 << 0; }
+    if position[1] == 31 { faces |= 1 << 1; }
+    if position[2] == 0 { faces |= 1 << 2; }
+    if position[2] == 31 { faces |= 1 << 3; }
+    if position[0] == 0 { faces |= 1 << 4; }
+    if position[0] == 31 { faces |= 1 << 5; }
+    faces
+}
+
+pub fn connected(connections: u64, a: usize, b: usize) -> bool {
+    connections & (1 << (a * 6 + b)) != 0
+}
@@ -0,0 +1,167 @@
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::atlas::Tiles;
+use crate::block::{BlockId, FIRST_CUSTOM};
+use crate::mesh::FACES;
+use crate::model;
+
+// list of the custom blocks, their ids are given in this order starting at `FIRST_CUSTOM`
+const REGISTRY: &str = "models/blocks.json";
+
+#[derive(Deserialize)]
+struct BlockEntry {
+    name: String,
+    // path to a `.json` cuboid list or a `.gltf`/`.glb` mesh inside of `assets`
+    model: String,
+    // texture of `assets/textures/blocks` used by glTF models
+    #[serde(default)]
+    texture: String,
+}
+
+// cuboids in 16th of a block like in `from: [0, 0, 0], to: [16, 8, 16]` for a slab
+#[derive(Deserialize)]
+struct JsonModel {
+    cuboids: Vec<JsonCuboid>,
+}
+
+#[derive(Deserialize)]
+struct JsonCuboid {
+    from: [f32; 3],
+    to: [f32; 3],
+    texture: String,
+}
+
+/// geometry of a custom block, positions are relative to the block and uvs to the tile of the vertex
+#[derive(Default)]
+pub struct CustomModel {
+    pub name: String,
+    pub positions: Vec<[f32; 3]>,
+    // index into `FACES` of the direction closest to the normal of the vertex
+    pub faces: Vec<usize>,
+    pub tiles: Vec<u16>,
+    pub uvs: Vec<[f32; 2]>,
+    pub indices: Vec<u32>,
+}
+
+/// models of all blocks from `FIRST_CUSTOM` on
+#[derive(Default)]
+pub struct CustomModels {
+    pub models: Vec<CustomModel>,
+}
+impl CustomModels {
+    pub fn get(&self, block: BlockId) -> Option<&CustomModel> {
+        if block < FIRST_CUSTOM {
+            return None;
+        }
+        self.models.get((block - FIRST_CUSTOM) as usize)
+    }
+}
+
+fn load_json(path: &Path, tiles: &Tiles, model: &mut CustomModel) -> Result<(), String> {
+    let file = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let json: JsonModel = serde_json::from_str(&file).map_err(|e| e.to_string())?;
+
+    for cuboid in json.cuboids.iter() {
+        let min = [cuboid.from[0] / 16.0, cuboid.from[1] / 16.0, cuboid.from[2] / 16.0];
+        let max = [cuboid.to[0] / 16.0, cuboid.to[1] / 16.0, cuboid.to[2] / 16.0];
+        let tile = tiles.tile(&cuboid.texture);
+
+        for (face_index, face) in FACES.iter().enumerate() {
+            let [u, v] = model::face_axes(face_index);
+            let positions_len = model.positions.len() as u32;
+
+            for corner in face.corners.iter() {
+                let mut position = [0.0; 3];
+                for axis in 0..3 {
+                    position[axis] = min[axis] + corner[axis] * (max[axis] - min[axis]);
+                }
+                model.positions.push(position);
+                model.faces.push(face_index);
+                model.tiles.push(tile);
+                model.uvs.push([position[u], position[v]]);
+            }
+            for index in face.indices.iter() {
+                model.indices.push(positions_len + index);
+            }
+        }
+    }
+    Ok(())
+}
+
+// glTF models have their origin in the bottom center of the block
+fn load_gltf(path: &Path, texture: &str, tiles: &Tiles, model: &mut CustomModel) -> Result<(), String> {
+    let (document, buffers, _) = gltf::import(path).map_err(|e| e.to_string())?;
+    let tile = tiles.tile(texture);
+
+    for mesh in document.meshes() {
+        for primitive in mesh.primitives() {
+            let reader = primitive.reader(|buffer| Some(&buffers[buffer.index()]));
+            let positions_len = model.positions.len() as u32;
+
+            let positions: Vec<[f32; 3]> = reader
+                .read_positions()
+                .ok_or("mesh without positions")?
+                .map(|p| [p[0] + 0.5, p[1], p[2] + 0.5])
+                .collect();
+            let faces: Vec<usize> = match reader.read_normals() {
+                Some(normals) => normals.map(model::nearest_face).collect(),
+                None => vec![1; positions.len()],
+            };
+            // the uvs are clamped to the tile, the texture does not repeat
+            let uvs: Vec<[f32; 2]> = match reader.read_tex_coords(0) {
+                Some(uvs) => uvs.into_f32().map(|uv| [uv[0].clamp(0.0, 1.0), uv[1].clamp(0.0, 1.0)]).collect(),
+                None => vec![[0.5, 0.5]; positions.len()],
+            };
+            let indices: Vec<u32> = match reader.read_indices() {
+                Some(indices) => indices.into_u32().collect(),
+                None => (0..positions.len() as u32).collect(),
+            };
+
+            model.tiles.extend(std::iter::repeat_n(tile, positions.len()));
+            model.positions.extend(positions);
+            model.faces.extend(faces);
+            model.uvs.extend(uvs);
+            model.indices.extend(indices.iter().map(|index| positions_len + index));
+        }
+    }
+    Ok(())
+}
+
+/// reads the custom blocks from the registry inside of `assets`, blocks whose model can not be loaded stay invisible,
+/// the problems are returned as warnings
+pub fn load_models(assets: &Path, tiles: &Tiles) -> (CustomModels, Vec<String>) {
+    let mut models = CustomModels::default();
+    let mut warnings = Vec::new();
+
+    let registry: Vec<BlockEntry> = match std::fs::read_to_string(assets.join(REGISTRY)) {
+        Ok(file) => match serde_json::from_str(&file) {
+            Ok(registry) => registry,
+            Err(e) => {
+                warnings.push(format!("invalid block registry {}: {}", REGISTRY, e));
+                Vec::new()
+            }
+        },
+        Err(_) => Vec::new(),
+    };
+
+    for entry in registry {
+        let path = assets.join(&entry.model);
+        let mut model = CustomModel {name: entry.name, ..Default::default()};
+
+        let result = if entry.model.ends_with(".json") {
+            load_json(&path, tiles, &mut model)
+        } else {
+            load_gltf(&path, &entry.texture, tiles, &mut model)
+        };
+        if let Err(e) = result {
+            warnings.push(format!("could not load the model of {}: {}", model.name, e));
+            model = CustomModel {name: model.name, ..Default::default()};
+        }
+
+        models.models.push(model);
+    }
+
+    (models, warnings)
+}
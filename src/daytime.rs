@@ -0,0 +1,142 @@
+use std::f32::consts::TAU;
+
+use bevy::prelude::*;
+
+use crate::render::ChunkMaterial;
+
+// the colours of the sky and the ambient light at some points of the day, 0.0 is midnight and 0.5 noon
+const SKY_COLORS: [(f32, [f32; 3]); 6] = [
+    (0.0, [0.01, 0.01, 0.04]),
+    (0.2, [0.02, 0.02, 0.08]),
+    (0.27, [0.85, 0.5, 0.3]),
+    (0.35, [0.5, 0.75, 1.0]),
+    (0.65, [0.5, 0.75, 1.0]),
+    (0.73, [0.9, 0.45, 0.25]),
+];
+const AMBIENT_COLORS: [(f32, [f32; 3]); 6] = [
+    (0.0, [0.06, 0.06, 0.12]),
+    (0.2, [0.06, 0.06, 0.12]),
+    (0.27, [0.25, 0.2, 0.2]),
+    (0.35, [0.3, 0.3, 0.3]),
+    (0.65, [0.3, 0.3, 0.3]),
+    (0.73, [0.25, 0.18, 0.18]),
+];
+
+// how far the time jumps when it is set by hand
+const STEP: f32 = 1.0 / 24.0;
+
+pub struct TimeOfDay {
+    // 0.0 to 1.0, 0.0 is midnight and 0.5 noon
+    pub time: f32,
+    // length of a whole day in seconds
+    pub day_length: f32,
+    pub paused: bool,
+}
+impl Default for TimeOfDay {
+    fn default() -> Self {
+        TimeOfDay {time: 0.3, day_length: 600.0, paused: false}
+    }
+}
+impl TimeOfDay {
+    /// points towards the sun, it rises in the east (x+) and sets in the west
+    pub fn sun_direction(&self) -> Vec3 {
+        let angle = (self.time - 0.25) * TAU;
+        Vec3::new(angle.cos(), angle.sin(), 0.2).normalize()
+    }
+
+    /// 1.0 at day, 0.0 at night, fades while the sun is close to the horizon
+    pub fn sun_intensity(&self) -> f32 {
+        ((self.sun_direction().y + 0.1) / 0.3).clamp(0.0, 1.0)
+    }
+
+    pub fn sky_color(&self) -> Color {
+        let [r, g, b] = interpolate(&SKY_COLORS, self.time);
+        Color::rgb(r, g, b)
+    }
+
+    pub fn ambient_color(&self) -> Color {
+        let [r, g, b] = interpolate(&AMBIENT_COLORS, self.time);
+        Color::rgb(r, g, b)
+    }
+}
+
+// linear interpolation between the colours before and after `time`, wraps around midnight
+fn interpolate(colors: &[(f32, [f32; 3])], time: f32) -> [f32; 3] {
+    let next = colors.iter().position(|(t, _)| *t > time).unwrap_or(0);
+    let previous = (next + colors.len() - 1) % colors.len();
+
+    let (start, from) = colors[previous];
+    let (mut end, to) = colors[next];
+    if end <= start {
+        end += 1.0;
+    }
+    let time = if time < start { time + 1.0 } else { time };
+    let factor = (time - start) / (end - start);
+
+    [
+        from[0] + (to[0] - from[0]) * factor,
+        from[1] + (to[1] - from[1]) * factor,
+        from[2] + (to[2] - from[2]) * factor,
+    ]
+}
+
+pub fn advance_time(
+    time: Res<Time>,
+    mut time_of_day: ResMut<TimeOfDay>,
+) {
+    if time_of_day.paused {
+        return;
+    }
+    time_of_day.time = (time_of_day.time + time.delta_seconds() / time_of_day.day_length).rem_euclid(1.0);
+}
+
+/// P pauses the day, [ and ] set the time back or forth by an hour
+pub fn time_controls(
+    input: Res<Input<KeyCode>>,
+    windows: Res<Windows>,
+    mut time_of_day: ResMut<TimeOfDay>,
+) {
+    // typing into the menu or the console does not change the time
+    if !windows.get_primary().unwrap().cursor_locked() {
+        return;
+    }
+    if input.just_pressed(KeyCode::P) {
+        time_of_day.paused = !time_of_day.paused;
+    }
+    if input.just_pressed(KeyCode::LBracket) {
+        time_of_day.time = (time_of_day.time - STEP).rem_euclid(1.0);
+    }
+    if input.just_pressed(KeyCode::RBracket) {
+        time_of_day.time = (time_of_day.time + STEP).rem_euclid(1.0);
+    }
+}
+
+/// colours the sky and passes the sun to the chunk shader
+pub fn update_sky(
+    time_of_day: Res<TimeOfDay>,
+    mut clear_color: ResMut<ClearColor>,
+    materials: Res<crate::Materials>,
+    mut chunk_materials: ResMut<Assets<ChunkMaterial>>,
+    camera: Query<&Transform, (With<crate::Camera>, Without<crate::Light>)>,
+    mut light: Query<(&mut Transform, &mut bevy::pbr::Light), With<crate::Light>>,
+) {
+    let sun_direction = time_of_day.sun_direction();
+    let sun_intensity = time_of_day.sun_intensity();
+
+    clear_color.0 = time_of_day.sky_color();
+
+    for handle in [&materials.blocks, &materials.cutout_blocks, &materials.translucent_blocks].iter() {
+        if let Some(material) = chunk_materials.get_mut(*handle) {
+            material.sun = sun_direction.extend(sun_intensity);
+            material.ambient = time_of_day.ambient_color();
+        }
+    }
+
+    // the point light follows the camera at the position of the sun
+    for camera in camera.iter() {
+        for (mut transform, mut light) in light.iter_mut() {
+            transform.translation = camera.translation + sun_direction * 500.0;
+            light.color = Color::rgb(sun_intensity, sun_intensity, sun_intensity);
+        }
+    }
+}
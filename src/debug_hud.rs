@@ -0,0 +1,117 @@
+use bevy::diagnostic::{Diagnostics, FrameTimeDiagnosticsPlugin};
+use bevy::prelude::*;
+
+use voxel::coords::BlockPos;
+use voxel::fluid::FluidSimulation;
+use voxel::world::World;
+
+use crate::chunk::ChunkMesh;
+use crate::controll::Builder;
+
+// seconds between two updates of the text, numbers that change every frame can not be read
+const UPDATE_INTERVAL: f64 = 0.25;
+
+/// F3 shows the frame rate, the position of the camera and what is loaded and queued
+#[derive(Default)]
+pub struct DebugHud {
+    pub open: bool,
+    last_update: f64,
+}
+
+pub struct DebugText;
+
+pub fn setup_debug_hud(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands
+        .spawn_bundle(TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {right: Val::Px(20.0), top: Val::Px(20.0), ..Default::default()},
+                ..Default::default()
+            },
+            text: Text::with_section(
+                String::new(),
+                TextStyle {font: asset_server.load("fonts/DejaVuSansMono.ttf"), font_size: 18.0, color: Color::WHITE},
+                Default::default(),
+            ),
+            visible: Visible {is_visible: false, is_transparent: true},
+            ..Default::default()
+        })
+        .insert(DebugText);
+}
+
+pub fn toggle_debug_hud(keys: Res<Input<KeyCode>>, mut hud: ResMut<DebugHud>) {
+    if keys.just_pressed(KeyCode::F3) {
+        hud.open = !hud.open;
+        // shows the numbers right away instead of after the next interval
+        hud.last_update = f64::NEG_INFINITY;
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn update_debug_hud(
+    time: Res<Time>,
+    diagnostics: Res<Diagnostics>,
+    meshes: Res<Assets<Mesh>>,
+    fluids: Option<Res<FluidSimulation>>,
+    mut hud: ResMut<DebugHud>,
+    camera: Query<&Transform, With<crate::Camera>>,
+    builder: Query<&Builder>,
+    world: Query<&World>,
+    chunk_meshes: Query<(&Handle<Mesh>, &Visible), With<ChunkMesh>>,
+    mut text: Query<(&mut Text, &mut Visible), (With<DebugText>, Without<ChunkMesh>)>,
+) {
+    for (_, mut visible) in text.iter_mut() {
+        if visible.is_visible != hud.open {
+            visible.is_visible = hud.open;
+        }
+    }
+    if !hud.open || time.seconds_since_startup() - hud.last_update < UPDATE_INTERVAL {
+        return;
+    }
+    hud.last_update = time.seconds_since_startup();
+
+    let mut lines: Vec<String> = Vec::new();
+
+    let fps = diagnostics.get(FrameTimeDiagnosticsPlugin::FPS).and_then(|fps| fps.average());
+    let frame_time = diagnostics.get(FrameTimeDiagnosticsPlugin::FRAME_TIME).and_then(|frame_time| frame_time.average());
+    if let (Some(fps), Some(frame_time)) = (fps, frame_time) {
+        lines.push(format!("{:.0} fps, {:.2} ms", fps, frame_time * 1000.0));
+    }
+
+    if let Some(camera) = camera.iter().next() {
+        let position = [camera.translation.x, camera.translation.y, camera.translation.z];
+        let (chunk, local) = BlockPos::from_point(position).split();
+        lines.push(format!("position {:.1} {:.1} {:.1}", position[0], position[1], position[2]));
+        lines.push(format!("chunk {} {} {}, in chunk {} {} {}", chunk.x, chunk.y, chunk.z, local.x, local.y, local.z));
+    }
+
+    for world in world.iter() {
+        if let Some(builder) = builder.iter().next() {
+            let target = builder.target();
+            match world.get(target) {
+                Some(block) => lines.push(format!("target {:?}: block {} state {}", target, block.id, block.state)),
+                None => lines.push(format!("target {:?}: not loaded", target)),
+            }
+        }
+
+        let dirty = world.chunk_index.iter().filter(|chunk| chunk.dirty).count();
+        lines.push(format!("chunks {}, waiting for a mesh {}", world.chunk_index.len(), dirty));
+    }
+
+    let mut vertices = 0;
+    let mut visible_meshes = 0;
+    for (mesh, visible) in chunk_meshes.iter() {
+        vertices += meshes.get(mesh).map(|mesh| mesh.count_vertices()).unwrap_or(0);
+        visible_meshes += visible.is_visible as usize;
+    }
+    lines.push(format!("meshes {} ({} visible), vertices {}", chunk_meshes.iter().count(), visible_meshes, vertices));
+
+    // multiplayer worlds are simulated by the server
+    if let Some(fluids) = fluids {
+        lines.push(format!("fluid cells waiting {}", fluids.active_cells()));
+    }
+
+    for (mut text, _) in text.iter_mut() {
+        text.sections[0].value = lines.join("\n");
+    }
+}
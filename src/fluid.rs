@@ -0,0 +1,216 @@
+use std::collections::HashSet;
+
+use crate::block::{self, BlockId};
+use crate::world::World;
+
+// seconds between two simulation steps
+pub const TICK: f64 = 0.2;
+
+// fluid levels stored in the block state:
+// 0 is a source block, flowing fluid stores its distance to the next source
+// and fluid that is fed from above gets the FALLING flag
+pub const SOURCE: u8 = 0;
+pub const FALLING: u8 = 8;
+
+const NEIGHBOURS: [[i32; 3]; 6] = [
+    [0, -1, 0],
+    [0, 1, 0],
+    [-1, 0, 0],
+    [1, 0, 0],
+    [0, 0, -1],
+    [0, 0, 1],
+];
+const HORIZONTAL: [[i32; 3]; 4] = [[-1, 0, 0], [1, 0, 0], [0, 0, -1], [0, 0, 1]];
+
+/// cells that may change during the next simulation steps
+#[derive(Default)]
+pub struct FluidSimulation {
+    active: HashSet<[i32; 3]>,
+    tick: u32,
+}
+impl FluidSimulation {
+    // wakes up the position and all of its neighbours
+    pub fn activate(&mut self, position: [i32; 3]) {
+        self.active.insert(position);
+        for offset in NEIGHBOURS.iter() {
+            self.active.insert(add(position, *offset));
+        }
+    }
+
+    /// the number of cells waiting for the next step
+    pub fn active_cells(&self) -> usize {
+        self.active.len()
+    }
+
+    /// advances all active fluid cells by one step
+    pub fn step(&mut self, world: &mut World) {
+        self.tick = self.tick.wrapping_add(1);
+        let tick = self.tick;
+
+        let active: Vec<[i32; 3]> = self.active.drain().collect();
+
+        for position in active.iter() {
+            let block = match world.get_block(*position) {
+                Some(block) if block::is_fluid(block) => block,
+                _ => continue,
+            };
+
+            // waits for the next step of this fluid
+            if !tick.is_multiple_of(ticks_per_step(block)) {
+                self.active.insert(*position);
+                continue;
+            }
+
+            update_cell(world, self, *position, block);
+        }
+    }
+}
+
+// how far a fluid flows horizontally and how much distance one block adds
+fn max_distance(fluid: BlockId) -> u8 {
+    if fluid == block::LAVA { 6 } else { 7 }
+}
+fn decay(fluid: BlockId) -> u8 {
+    if fluid == block::LAVA { 2 } else { 1 }
+}
+// lava is slower than water
+fn ticks_per_step(fluid: BlockId) -> u32 {
+    if fluid == block::LAVA { 3 } else { 1 }
+}
+
+fn add(a: [i32; 3], b: [i32; 3]) -> [i32; 3] {
+    [a[0] + b[0], a[1] + b[1], a[2] + b[2]]
+}
+
+// distance used for spreading, falling fluid acts like a source
+fn distance(level: u8) -> u8 {
+    if level & FALLING != 0 { 0 } else { level }
+}
+
+fn update_cell(
+    world: &mut World,
+    simulation: &mut FluidSimulation,
+    position: [i32; 3],
+    fluid: BlockId,
+) {
+    let mut level = world.get_state(position);
+
+    // lava touching water hardens
+    if fluid == block::LAVA
+    && NEIGHBOURS.iter().any(|offset| world.get_block(add(position, *offset)) == Some(block::WATER)) {
+        world.set_block(position, block::STONE);
+        simulation.activate(position);
+        return;
+    }
+
+    // flowing fluid has to be fed by a neighbour
+    if level != SOURCE {
+        match fed_level(world, position, fluid) {
+            None => {
+                world.set_block(position, block::AIR);
+                simulation.activate(position);
+                return;
+            }
+            Some(new_level) => {
+                if new_level != level {
+                    world.set_state(position, new_level);
+                    simulation.activate(position);
+                    level = new_level;
+                }
+            }
+        }
+    }
+
+    spread(world, simulation, position, fluid, level);
+}
+
+// level a flowing cell should have, `None` if nothing feeds it anymore
+fn fed_level(world: &World, position: [i32; 3], fluid: BlockId) -> Option<u8> {
+    if world.get_block(add(position, [0, 1, 0])) == Some(fluid) {
+        return Some(FALLING);
+    }
+
+    HORIZONTAL
+        .iter()
+        .map(|offset| add(position, *offset))
+        .filter(|neighbour| world.get_block(*neighbour) == Some(fluid) && !can_fall(world, *neighbour, fluid))
+        .map(|neighbour| distance(world.get_state(neighbour)) + decay(fluid))
+        .filter(|level| *level <= max_distance(fluid))
+        .min()
+}
+
+// fluid flows down instead of sideways as long as there is room below it
+fn can_fall(world: &World, position: [i32; 3], fluid: BlockId) -> bool {
+    let below = add(position, [0, -1, 0]);
+    match world.get_block(below) {
+        Some(block::AIR) => true,
+        Some(block) if block == fluid => world.get_state(below) != SOURCE,
+        _ => false,
+    }
+}
+
+fn spread(
+    world: &mut World,
+    simulation: &mut FluidSimulation,
+    position: [i32; 3],
+    fluid: BlockId,
+    level: u8,
+) {
+    let below = add(position, [0, -1, 0]);
+    match world.get_block(below) {
+        Some(block::AIR) => {
+            flow_into(world, simulation, below, fluid, FALLING);
+            return;
+        }
+        Some(block) if block == fluid => {
+            if world.get_state(below) != SOURCE && world.get_state(below) != FALLING {
+                flow_into(world, simulation, below, fluid, FALLING);
+            }
+            return;
+        }
+        Some(block) if block::is_fluid(block) => {
+            world.set_block(below, block::STONE);
+            simulation.activate(below);
+            return;
+        }
+        None => return,
+        _ => {}
+    }
+
+    let next = distance(level) + decay(fluid);
+    if next > max_distance(fluid) {
+        return;
+    }
+
+    for offset in HORIZONTAL.iter() {
+        let neighbour = add(position, *offset);
+        match world.get_block(neighbour) {
+            Some(block::AIR) => flow_into(world, simulation, neighbour, fluid, next),
+            Some(block) if block == fluid => {
+                let neighbour_level = world.get_state(neighbour);
+                if neighbour_level != SOURCE && neighbour_level & FALLING == 0 && neighbour_level > next {
+                    flow_into(world, simulation, neighbour, fluid, next);
+                }
+            }
+            Some(block) if block::is_fluid(block) => {
+                world.set_block(neighbour, block::STONE);
+                simulation.activate(neighbour);
+            }
+            _ => {}
+        }
+    }
+}
+
+fn flow_into(
+    world: &mut World,
+    simulation: &mut FluidSimulation,
+    position: [i32; 3],
+    fluid: BlockId,
+    level: u8,
+) {
+    if world.get_block(position) != Some(fluid) {
+        world.set_block(position, fluid);
+    }
+    world.set_state(position, level);
+    simulation.activate(position);
+}
@@ -0,0 +1,130 @@
+use bevy::prelude::*;
+
+use voxel::block;
+use voxel::mesh;
+use voxel::world::World;
+
+use crate::chunk::BlockChanged;
+
+// blocks per second squared
+const GRAVITY: f32 = 30.0;
+const MAX_VELOCITY: f32 = 40.0;
+
+/// a block that left `Chunk::blocks` and falls until it lands on a solid block
+pub struct FallingBlock {
+    pub block: block::BlockId,
+    pub velocity: f32,
+    // where it started to fall, it goes back there if it can not be placed where it lands
+    pub start: [i32; 3],
+}
+
+/// turns blocks with gravity into falling entities when the block below them is gone
+pub fn start_falling(
+    mut commands: Commands,
+    mut events: EventReader<BlockChanged>,
+    mut world: Query<&mut World, With<World>>,
+    materials: Res<crate::Materials>,
+    atlas: Res<crate::render::BlockAtlas>,
+    mut meshes: ResMut<Assets<Mesh>>,
+) {
+    for mut world in world.iter_mut() {
+        for event in events.iter() {
+            // the changed block itself and the one resting on it
+            let position = event.position;
+            let above = [position[0], position[1] + 1, position[2]];
+
+            for position in [position, above].iter() {
+                let block = match world.get_block(*position) {
+                    Some(block) if block::has_gravity(block) => block,
+                    _ => continue,
+                };
+
+                let below = [position[0], position[1] - 1, position[2]];
+                match world.get_block(below) {
+                    Some(below) if !block::is_solid(below) => {}
+                    _ => continue,
+                }
+                // above a hole down to the bottom of the loaded world it stays, see `fall`
+                if !lands_in_world(&world, *position) {
+                    continue;
+                }
+
+                // the now empty position triggers the block above in the next frame
+                world.set_block(*position, block::AIR);
+
+                commands
+                    .spawn_bundle(crate::render::chunk_bundle(
+                        meshes.add(crate::render::packed_mesh(mesh::create_block_mesh(block, &atlas.tiles))),
+                        &materials,
+                        Transform::from_translation(Vec3::new(
+                            position[0] as f32,
+                            position[1] as f32,
+                            position[2] as f32,
+                        )),
+                        false,
+                    ))
+                    .insert(materials.blocks.clone())
+                    .insert(FallingBlock { block, velocity: 0.0, start: *position });
+            }
+        }
+    }
+}
+
+/// moves falling blocks and puts them back into the world once they land
+pub fn fall(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut world: Query<&mut World, With<World>>,
+    mut falling_blocks: Query<(Entity, &mut FallingBlock, &mut Transform)>,
+) {
+    for mut world in world.iter_mut() {
+        for (entity, mut falling_block, mut transform) in falling_blocks.iter_mut() {
+            falling_block.velocity = (falling_block.velocity + GRAVITY * time.delta_seconds()).min(MAX_VELOCITY);
+
+            // never skips a block, even on long frames
+            let step = (falling_block.velocity * time.delta_seconds()).min(0.9);
+            transform.translation.y -= step;
+
+            let x = transform.translation.x as i32;
+            let z = transform.translation.z as i32;
+            let bottom = transform.translation.y.floor() as i32;
+
+            match world.get_block([x, bottom, z]) {
+                // fell out of the loaded world, `start_falling` leaves it where it is from then on
+                None => {
+                    world.set_block(falling_block.start, falling_block.block);
+                    commands.entity(entity).despawn();
+                }
+                Some(below) if block::is_solid(below) => {
+                    // lands on the first free block above its support
+                    let mut y = bottom + 1;
+                    while let Some(block) = world.get_block([x, y, z]) {
+                        if !block::is_solid(block) {
+                            break;
+                        }
+                        y += 1;
+                    }
+
+                    // the column above the support reaches into a chunk that is not loaded
+                    if !world.set_block([x, y, z], falling_block.block) {
+                        world.set_block(falling_block.start, falling_block.block);
+                    }
+                    commands.entity(entity).despawn();
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+// whether a solid block in the loaded world below the position catches a falling block
+fn lands_in_world(world: &World, position: [i32; 3]) -> bool {
+    let mut y = position[1] - 1;
+    while let Some(block) = world.get_block([position[0], y, position[2]]) {
+        if block::is_solid(block) {
+            return true;
+        }
+        y -= 1;
+    }
+    false
+}
@@ -0,0 +1,21 @@
+//! the world storage, terrain generation, lighting and meshing of the game without any windowing,
+//! the meshes are plain buffers of packed vertices, see `mesh`
+
+pub mod atlas;
+pub mod block;
+pub mod client;
+pub mod command_line;
+pub mod coords;
+pub mod culling;
+pub mod custom_model;
+pub mod fluid;
+pub mod light;
+pub mod lod;
+pub mod mesh;
+pub mod model;
+pub mod protocol;
+pub mod save;
+pub mod server;
+pub mod settings;
+pub mod storage;
+pub mod world;
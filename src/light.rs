@@ -0,0 +1,394 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use crate::block::{self, BlockId};
+use crate::world::World;
+
+// `Chunk::light` stores the skylight in the upper and the block light in the lower four bits
+pub const MAX_LIGHT: u8 = 15;
+pub const SKY: u8 = MAX_LIGHT << 4;
+
+pub fn sky(light: u8) -> u8 {
+    light >> 4
+}
+pub fn block_light(light: u8) -> u8 {
+    light & 0x0F
+}
+
+const DOWN: [i32; 3] = [0, -1, 0];
+const NEIGHBOURS: [[i32; 3]; 6] = [
+    [0, -1, 0],
+    [0, 1, 0],
+    [-1, 0, 0],
+    [1, 0, 0],
+    [0, 0, -1],
+    [0, 0, 1],
+];
+
+#[derive(Clone, Copy, PartialEq)]
+enum Channel {
+    Sky,
+    Block,
+}
+
+fn add(a: [i32; 3], b: [i32; 3]) -> [i32; 3] {
+    [a[0] + b[0], a[1] + b[1], a[2] + b[2]]
+}
+
+fn get(world: &World, position: [i32; 3], channel: Channel) -> u8 {
+    let light = world.get_light(position);
+    match channel {
+        Channel::Sky => sky(light),
+        Channel::Block => block_light(light),
+    }
+}
+
+fn set(world: &mut World, position: [i32; 3], channel: Channel, level: u8) {
+    let light = world.get_light(position);
+    let light = match channel {
+        Channel::Sky => (level << 4) | block_light(light),
+        Channel::Block => (light & 0xF0) | level,
+    };
+    world.set_light(position, light);
+}
+
+// how much light is lost when entering the block, `None` if it does not let light through
+fn attenuation(block: BlockId) -> Option<u8> {
+    if block::is_opaque(block) {
+        None
+    } else if block::is_fluid(block) {
+        Some(2)
+    } else {
+        Some(1)
+    }
+}
+
+// level the light of `level` has after moving into `block` in the given direction,
+// unobstructed sunlight keeps its full strength while falling down
+fn spread_level(level: u8, block: BlockId, direction: [i32; 3], channel: Channel) -> Option<u8> {
+    let attenuation = attenuation(block)?;
+    if channel == Channel::Sky && direction == DOWN && level == MAX_LIGHT && attenuation == 1 {
+        return Some(MAX_LIGHT);
+    }
+    Some(level.saturating_sub(attenuation))
+}
+
+// flood fills the light of all queued positions into their neighbours
+fn propagate(world: &mut World, mut queue: VecDeque<[i32; 3]>, channel: Channel) {
+    while let Some(position) = queue.pop_front() {
+        let level = get(world, position, channel);
+        if level <= 1 {
+            continue;
+        }
+
+        for offset in NEIGHBOURS.iter() {
+            let neighbour = add(position, *offset);
+            let block = match world.get_block(neighbour) {
+                Some(block) => block,
+                None => continue,
+            };
+
+            if let Some(new_level) = spread_level(level, block, *offset, channel) {
+                if new_level > get(world, neighbour, channel) {
+                    set(world, neighbour, channel, new_level);
+                    queue.push_back(neighbour);
+                }
+            }
+        }
+    }
+}
+
+// darkens everything that was lit by the queued positions and their old levels,
+// brighter neighbours that have to fill the gap again are added to `relight`
+fn remove(
+    world: &mut World,
+    mut queue: VecDeque<([i32; 3], u8)>,
+    channel: Channel,
+    relight: &mut VecDeque<[i32; 3]>,
+) {
+    while let Some((position, level)) = queue.pop_front() {
+        for offset in NEIGHBOURS.iter() {
+            let neighbour = add(position, *offset);
+            let block = match world.get_block(neighbour) {
+                Some(block) => block,
+                None => continue,
+            };
+
+            let neighbour_level = get(world, neighbour, channel);
+            if neighbour_level == 0 {
+                continue;
+            }
+
+            let lit_by_position = neighbour_level < level
+                || (channel == Channel::Sky && *offset == DOWN && level == MAX_LIGHT && neighbour_level == MAX_LIGHT);
+
+            if lit_by_position {
+                set(world, neighbour, channel, 0);
+                queue.push_back((neighbour, neighbour_level));
+
+                // light sources keep on shining
+                if channel == Channel::Block && block::light_emission(block) > 0 {
+                    set(world, neighbour, channel, block::light_emission(block));
+                    relight.push_back(neighbour);
+                }
+            } else {
+                relight.push_back(neighbour);
+            }
+        }
+    }
+}
+
+/// recalculates the light around a replaced block
+pub fn update_block(world: &mut World, position: [i32; 3]) {
+    let block = match world.get_block(position) {
+        Some(block) => block,
+        None => return,
+    };
+
+    for channel in [Channel::Sky, Channel::Block].iter() {
+        let mut relight: VecDeque<[i32; 3]> = VecDeque::new();
+
+        let old_level = get(world, position, *channel);
+        if old_level > 0 {
+            set(world, position, *channel, 0);
+            remove(world, VecDeque::from(vec![(position, old_level)]), *channel, &mut relight);
+        }
+
+        if *channel == Channel::Block && block::light_emission(block) > 0 {
+            set(world, position, *channel, block::light_emission(block));
+            relight.push_back(position);
+        }
+
+        // light flows back in from the neighbours
+        for offset in NEIGHBOURS.iter() {
+            relight.push_back(add(position, *offset));
+        }
+
+        propagate(world, relight, *channel);
+    }
+}
+
+/// lights all loaded chunks from scratch
+pub fn light_world(world: &mut World) {
+    // sunlight falls straight down until it hits an opaque block
+    let mut columns: HashMap<[i32; 2], Vec<usize>> = HashMap::new();
+    for (index, chunk) in world.chunk_index.iter().enumerate() {
+        columns.entry([chunk.x, chunk.z]).or_default().push(index);
+    }
+
+    // world y of the highest opaque block of every column
+    let mut heightmap: HashMap<[i32; 2], i32> = HashMap::new();
+    let mut emitters: VecDeque<[i32; 3]> = VecDeque::new();
+
+    for (column, chunks) in columns.iter_mut() {
+        chunks.sort_by_key(|chunk| -world.chunk_index[*chunk].y);
+
+        for x in 0..32 {
+            for z in 0..32 {
+                let mut level = MAX_LIGHT;
+                let mut height = i32::MIN;
+
+                for chunk in chunks.iter() {
+                    let chunk = &mut world.chunk_index[*chunk];
+                    for y in (0..32).rev() {
+                        let block = chunk.get([x, y, z]).id;
+
+                        match attenuation(block) {
+                            None => {
+                                if height == i32::MIN {
+                                    height = chunk.y * 32 + y as i32;
+                                }
+                                level = 0;
+                            }
+                            Some(1) => {}
+                            Some(attenuation) => level = level.saturating_sub(attenuation),
+                        }
+
+                        let emission = block::light_emission(block);
+                        if emission > 0 {
+                            emitters.push_back([chunk.x * 32 + x as i32, chunk.y * 32 + y as i32, chunk.z * 32 + z as i32]);
+                        }
+
+                        chunk.light[x][y][z] = (level << 4) | emission;
+                    }
+                }
+
+                heightmap.insert([column[0] * 32 + x as i32, column[1] * 32 + z as i32], height);
+            }
+        }
+    }
+
+    // sunlight only spreads sideways where a neighbouring column is shadowed
+    let bottom = world.chunk_index.iter().map(|chunk| chunk.y * 32).min().unwrap_or(0);
+    let mut sunlit: VecDeque<[i32; 3]> = VecDeque::new();
+    for (column, height) in heightmap.iter() {
+        for offset in [[-1, 0], [1, 0], [0, -1], [0, 1]].iter() {
+            let neighbour_height = match heightmap.get(&[column[0] + offset[0], column[1] + offset[1]]) {
+                Some(neighbour_height) => *neighbour_height,
+                None => continue,
+            };
+
+            for y in (*height + 1).max(bottom)..neighbour_height {
+                sunlit.push_back([column[0], y, column[1]]);
+            }
+        }
+    }
+
+    propagate(world, sunlit, Channel::Sky);
+    propagate(world, emitters, Channel::Block);
+
+    // everything gets meshed after the world is lit
+    for chunk in world.chunk_index.iter_mut() {
+        chunk.dirty = false;
+    }
+}
+
+/// lights the given chunks from scratch, the light of the other loaded chunks flows into them but
+/// stays as it is, much cheaper than `light_world` when a few chunks are added to a large world
+pub fn light_chunks(world: &mut World, positions: &[[i32; 3]]) {
+    let mut positions: Vec<[i32; 3]> = positions.iter()
+        .copied()
+        .filter(|position| world.get_chunk(*position).is_some())
+        .collect();
+    // sunlight comes from above, so the upper chunks of a column are lit first
+    positions.sort_by_key(|position| (-position[1], position[0], position[2]));
+    positions.dedup();
+    let lit: HashSet<[i32; 3]> = positions.iter().copied().collect();
+
+    let mut sunlit: VecDeque<[i32; 3]> = VecDeque::new();
+    let mut emitters: VecDeque<[i32; 3]> = VecDeque::new();
+
+    for position in positions.iter() {
+        // unobstructed sunlight falls in from the chunk above or from the open sky
+        let above = world.get_chunk([position[0], position[1] + 1, position[2]]);
+        let top: Vec<u8> = (0..32 * 32)
+            .map(|column| match above {
+                Some(above) if sky(above.light[column / 32][0][column % 32]) != MAX_LIGHT => 0,
+                _ => MAX_LIGHT,
+            })
+            .collect();
+
+        let chunk = world.get_chunk_mut(*position).unwrap();
+        for x in 0..32 {
+            for z in 0..32 {
+                let mut level = top[x * 32 + z];
+                for y in (0..32).rev() {
+                    let block = chunk.get([x, y, z]).id;
+                    match attenuation(block) {
+                        None => level = 0,
+                        Some(1) => {}
+                        Some(attenuation) => level = level.saturating_sub(attenuation),
+                    }
+
+                    let emission = block::light_emission(block);
+                    if emission > 0 {
+                        emitters.push_back([chunk.x * 32 + x as i32, chunk.y * 32 + y as i32, chunk.z * 32 + z as i32]);
+                    }
+                    chunk.light[x][y][z] = (level << 4) | emission;
+                }
+            }
+        }
+    }
+
+    for position in positions.iter() {
+        let chunk = world.get_chunk(*position).unwrap();
+        let origin = [chunk.x * 32, chunk.y * 32, chunk.z * 32];
+
+        // sunlight spreads sideways and out of the chunk where it is brighter than a neighbour could be
+        for x in 0..32 {
+            for y in 0..32 {
+                for z in 0..32 {
+                    let level = sky(chunk.light[x][y][z]);
+                    if level <= 1 {
+                        continue;
+                    }
+                    let on_border = [x, y, z].iter().any(|axis| *axis == 0 || *axis == 31);
+                    let spreads = on_border || NEIGHBOURS.iter().any(|offset| {
+                        let neighbour = [(x as i32 + offset[0]) as usize, (y as i32 + offset[1]) as usize, (z as i32 + offset[2]) as usize];
+                        attenuation(chunk.get(neighbour).id).is_some()
+                            && sky(chunk.light[neighbour[0]][neighbour[1]][neighbour[2]]) < level - 1
+                    });
+                    if spreads {
+                        sunlit.push_back([origin[0] + x as i32, origin[1] + y as i32, origin[2] + z as i32]);
+                    }
+                }
+            }
+        }
+
+        // the light of the neighbours flows in through the faces of the chunk
+        for offset in NEIGHBOURS.iter() {
+            let neighbour = add(*position, *offset);
+            if lit.contains(&neighbour) || world.get_chunk(neighbour).is_none() {
+                continue;
+            }
+            let axis = offset.iter().position(|axis| *axis != 0).unwrap();
+            // the layer of the neighbour touching the chunk
+            let layer = if offset[axis] > 0 { origin[axis] + 32 } else { origin[axis] - 1 };
+            for a in 0..32 {
+                for b in 0..32 {
+                    let mut block = origin;
+                    block[axis] = layer;
+                    block[(axis + 1) % 3] += a;
+                    block[(axis + 2) % 3] += b;
+
+                    let light = world.get_light(block);
+                    if sky(light) > 1 {
+                        sunlit.push_back(block);
+                    }
+                    if block_light(light) > 1 {
+                        emitters.push_back(block);
+                    }
+                }
+            }
+        }
+    }
+
+    propagate(world, sunlit, Channel::Sky);
+    propagate(world, emitters, Channel::Block);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::Block;
+    use crate::world::Chunk;
+
+    // the eight chunks around the origin with a floor full of holes, an overhang and a torch
+    fn world() -> World {
+        let mut world = World::new(0, 0);
+        for x in -1..1 {
+            for y in -1..1 {
+                for z in -1..1 {
+                    world.add_chunk(Chunk::new(x, y, z));
+                }
+            }
+        }
+        for x in -32..32 {
+            for z in -32..32 {
+                if (x * 7 + z * 3) % 11 != 0 {
+                    world.set_block([x, -10, z], block::STONE);
+                }
+                if x > -5 && x < 20 && z > -20 && z < 3 {
+                    world.set_block([x, 12, z], block::STONE);
+                }
+            }
+        }
+        world.set([3, 5, -4], Block::new(block::TORCH));
+        world
+    }
+
+    #[test]
+    fn lighting_a_chunk_again_matches_the_whole_world() {
+        let mut world = world();
+        light_world(&mut world);
+        let expected: Vec<[[[u8; 32]; 32]; 32]> = world.chunk_index.iter().map(|chunk| chunk.light).collect();
+
+        for position in [[0, 0, 0], [-1, -1, 0]].iter() {
+            world.get_chunk_mut(*position).unwrap().light = [[[0; 32]; 32]; 32];
+        }
+        light_chunks(&mut world, &[[0, 0, 0], [-1, -1, 0]]);
+
+        for (chunk, expected) in world.chunk_index.iter().zip(expected.iter()) {
+            assert!(chunk.light == *expected, "the light of the chunk at {:?} differs", [chunk.x, chunk.y, chunk.z]);
+        }
+    }
+}
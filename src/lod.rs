@@ -0,0 +1,277 @@
+use crate::atlas::Tiles;
+use crate::block::{self, BlockId, Shape};
+use crate::light;
+use crate::mesh::{self, MeshBuffers, MeshPass, Vertex, FACES};
+use crate::world::{Chunk, World};
+
+// downsampled levels, a block of level `lod` covers 2^lod blocks in every direction
+pub const MAX_LOD: usize = 3;
+
+// distance in blocks the camera has to move past a ring before the level changes back,
+// keeps chunks on the border of a ring from being remeshed over and over
+const HYSTERESIS: f32 = 8.0;
+
+pub struct LodSettings {
+    // horizontal distance in chunks from which on level 1, 2 and 3 are used
+    pub rings: [f32; MAX_LOD],
+}
+impl Default for LodSettings {
+    // the outer chunks of the default view distance of 2 already use level 1 and 2
+    fn default() -> Self {
+        LodSettings {rings: [2.0, 3.0, 4.0]}
+    }
+}
+impl LodSettings {
+    pub fn lod(&self, distance: f32) -> usize {
+        self.rings.iter().filter(|ring| distance >= **ring * 32.0).count()
+    }
+}
+
+/// picks the level of every chunk by its horizontal distance to the camera and marks the changed ones for remeshing
+pub fn update_levels(world: &mut World, settings: &LodSettings, camera: [f32; 2]) {
+    let mut changed: Vec<[i32; 3]> = Vec::new();
+
+    for chunk in world.chunk_index.iter_mut() {
+        let center = [chunk.x as f32 * 32.0 + 16.0, chunk.z as f32 * 32.0 + 16.0];
+        let distance = ((center[0] - camera[0]).powi(2) + (center[1] - camera[1]).powi(2)).sqrt();
+
+        let lod = chunk.lod
+            .max(settings.lod(distance - HYSTERESIS))
+            .min(settings.lod(distance + HYSTERESIS));
+
+        if lod != chunk.lod {
+            chunk.lod = lod;
+            changed.push([chunk.x, chunk.y, chunk.z]);
+        }
+    }
+
+    // the seams towards the neighbours change as well
+    for position in changed {
+        for offset in [[0, 0, 0], [0, -1, 0], [0, 1, 0], [0, 0, -1], [0, 0, 1], [-1, 0, 0], [1, 0, 0]].iter() {
+            let neighbour = [position[0] + offset[0], position[1] + offset[1], position[2] + offset[2]];
+            if let Some(chunk) = world.get_chunk_mut(neighbour) {
+                chunk.dirty = true;
+            }
+        }
+    }
+}
+
+/// block that represents a cube of 2^lod blocks, air if less than half of it is filled,
+/// otherwise the highest block inside of it so that the surface keeps its look,
+/// plants and torches would turn into whole cubes and count as air
+pub fn downsample_cell(chunk: &Chunk, lod: usize, cell: [usize; 3]) -> BlockId {
+    let size = 1 << lod;
+    let mut filled = 0;
+    let mut top = block::AIR;
+
+    for y in (cell[1] * size..(cell[1] + 1) * size).rev() {
+        for x in cell[0] * size..(cell[0] + 1) * size {
+            for z in cell[2] * size..(cell[2] + 1) * size {
+                let block = chunk.get([x, y, z]).id;
+                if block != block::AIR && block::shape(block) != Shape::Cross {
+                    filled += 1;
+                    if top == block::AIR {
+                        top = block;
+                    }
+                }
+            }
+        }
+    }
+
+    if filled * 2 >= size * size * size {
+        top
+    } else {
+        block::AIR
+    }
+}
+
+/// the blocks of a chunk at the given level, indexed by `[x][y][z]` of `32 >> lod` blocks per axis
+pub fn downsample(chunk: &Chunk, lod: usize) -> Vec<Vec<Vec<BlockId>>> {
+    let cells = 32 >> lod;
+    (0..cells)
+        .map(|x| {
+            (0..cells)
+                .map(|y| (0..cells).map(|z| downsample_cell(chunk, lod, [x, y, z])).collect())
+                .collect()
+        })
+        .collect()
+}
+
+// brightest skylight and block light of a cube of the world
+fn cell_light(world: &World, origin: [i32; 3], size: i32) -> u8 {
+    let mut sky = 0;
+    let mut block_light = 0;
+    for x in origin[0]..origin[0] + size {
+        for y in origin[1]..origin[1] + size {
+            for z in origin[2]..origin[2] + size {
+                let light = world.get_light([x, y, z]);
+                sky = sky.max(light::sky(light));
+                block_light = block_light.max(light::block_light(light));
+            }
+        }
+    }
+    (sky << 4) | block_light
+}
+
+/// mesh of a chunk at a downsampled level, faces towards chunks of other levels are always drawn
+/// and hang one cell further down as a skirt, which closes the cracks between the different resolutions
+pub fn create_lod_mesh(
+    chunk: usize,
+    world: &World,
+    pass: MeshPass,
+    tiles: &Tiles,
+) -> MeshBuffers {
+    let chunk = &world.chunk_index[chunk];
+    let lod = chunk.lod;
+    let size: i32 = 1 << lod;
+    let cells: i32 = 32 >> lod;
+
+    let grid = downsample(chunk, lod);
+
+    // neighbours of the same level in the order of `FACES`
+    let neighbours: Vec<Option<Vec<Vec<Vec<BlockId>>>>> = FACES
+        .iter()
+        .map(|face| {
+            world
+                .get_chunk([chunk.x + face.normal[0], chunk.y + face.normal[1], chunk.z + face.normal[2]])
+                .filter(|neighbour| neighbour.lod == lod)
+                .map(|neighbour| downsample(neighbour, lod))
+        })
+        .collect();
+    let other_level: Vec<bool> = FACES
+        .iter()
+        .map(|face| {
+            world
+                .get_chunk([chunk.x + face.normal[0], chunk.y + face.normal[1], chunk.z + face.normal[2]])
+                .map(|neighbour| neighbour.lod != lod)
+                .unwrap_or(false)
+        })
+        .collect();
+
+    let mut vertices: Vec<[u32; 3]> = Vec::new();
+    let mut indices: Vec<u32> = Vec::new();
+
+    for x1 in 0..cells {
+        for y1 in 0..cells {
+            for z1 in 0..cells {
+                let block = grid[x1 as usize][y1 as usize][z1 as usize];
+                if block == block::AIR {
+                    continue;
+                }
+
+                for (face_index, face) in FACES.iter().enumerate() {
+                    let nx = x1 + face.normal[0];
+                    let ny = y1 + face.normal[1];
+                    let nz = z1 + face.normal[2];
+
+                    let inside = (0..cells).contains(&nx) && (0..cells).contains(&ny) && (0..cells).contains(&nz);
+                    let neighbour = if inside {
+                        grid[nx as usize][ny as usize][nz as usize]
+                    } else {
+                        match &neighbours[face_index] {
+                            Some(neighbour) => neighbour[nx.rem_euclid(cells) as usize][ny.rem_euclid(cells) as usize][nz.rem_euclid(cells) as usize],
+                            None => block::AIR,
+                        }
+                    };
+
+                    if !mesh::face_visible(block, neighbour, pass) {
+                        continue;
+                    }
+
+                    let light = cell_light(
+                        world,
+                        [
+                            chunk.x * 32 + nx * size,
+                            chunk.y * 32 + ny * size,
+                            chunk.z * 32 + nz * size,
+                        ],
+                        size,
+                    );
+
+                    // side faces towards another level reach down into the cell below
+                    let skirt = !inside && other_level[face_index] && face.normal[1] == 0;
+
+                    let vertices_len = vertices.len() as u32;
+                    for (i, corner) in face.corners.iter().enumerate() {
+                        let bottom = if skirt && corner[1] == 0.0 { 1.0 } else { 0.0 };
+                        vertices.push(mesh::pack_vertex(&Vertex {
+                            position: [
+                                (x1 as f32 + corner[0]) * size as f32,
+                                (y1 as f32 + corner[1] - bottom) * size as f32,
+                                (z1 as f32 + corner[2]) * size as f32,
+                            ],
+                            face: face_index,
+                            tile: tiles.tile(block::texture(block)),
+                            uv: [(i % 2) as f32, (i / 2) as f32],
+                            light,
+                            ao: 3,
+                            animation: block::animation(block),
+                        }));
+                    }
+
+                    for index in face.indices.iter() {
+                        indices.push(vertices_len + index);
+                    }
+                }
+            }
+        }
+    }
+
+    MeshBuffers {vertices, indices}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::Block;
+
+    #[test]
+    fn rings_reach_into_the_default_view_distance() {
+        let settings = LodSettings::default();
+        // the next chunk keeps every block, the diagonal one two chunks away does not
+        assert_eq!(settings.lod(32.0), 0);
+        assert!(settings.lod(2.0 * 32.0 * 2.0f32.sqrt()) > 0);
+    }
+
+    #[test]
+    fn plants_do_not_fill_cells() {
+        let mut chunk = Chunk::new(0, 0, 0);
+        for x in 0..2 {
+            for y in 0..2 {
+                for z in 0..2 {
+                    chunk.set([x, y, z], Block::new(block::FLOWER));
+                    chunk.set([x + 2, y, z], Block::new(block::STONE));
+                }
+            }
+        }
+        assert_eq!(downsample_cell(&chunk, 1, [0, 0, 0]), block::AIR);
+        assert_eq!(downsample_cell(&chunk, 1, [1, 0, 0]), block::STONE);
+    }
+
+    #[test]
+    fn seams_towards_other_levels_get_skirts() {
+        let mut world = World::new(0, 0);
+        world.add_chunk(Chunk::new(0, 0, 0));
+        world.add_chunk(Chunk::new(1, 0, 0));
+        world.chunk_index[0].lod = 1;
+        for y in 4..6 {
+            for z in 0..2 {
+                world.set_block([30, y, z], block::STONE);
+                world.set_block([31, y, z], block::STONE);
+            }
+        }
+
+        let lowest = |world: &World| {
+            create_lod_mesh(0, world, MeshPass::Opaque, &Tiles::default())
+                .vertices
+                .iter()
+                .map(|vertex| mesh::unpack_vertex(*vertex).position[1])
+                .fold(f32::INFINITY, f32::min)
+        };
+        assert_eq!(lowest(&world), 2.0);
+
+        // the same level on both sides does not need one
+        world.chunk_index[1].lod = 1;
+        assert_eq!(lowest(&world), 4.0);
+    }
+}
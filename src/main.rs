@@ -1,41 +1,202 @@
 use bevy::prelude::*;
+use bevy::core::FixedTimestep;
+
+use voxel::save::{WorldMeta, WorldSave};
+use voxel::settings::Settings;
 
 pub struct Materials {
-    pub blocks: Handle<StandardMaterial>,
+    pub blocks: Handle<render::ChunkMaterial>,
+    pub cutout_blocks: Handle<render::ChunkMaterial>,
+    pub translucent_blocks: Handle<render::ChunkMaterial>,
+    pub chunk_pipeline: Handle<bevy::render::pipeline::PipelineDescriptor>,
+    // does not write depth so that translucent blocks behind each other stay visible
+    pub translucent_pipeline: Handle<bevy::render::pipeline::PipelineDescriptor>,
 }
 
 mod chunk;
+mod cli;
+mod console;
 mod controll;
+mod daytime;
+mod debug_hud;
+mod gravity;
+mod menu;
+mod network;
 mod player_input;
+mod render;
+mod visibility;
 
 use player_input::*;
 
 pub struct Camera;
 pub struct Light;
 
+// exits the process with a message, for errors before the window opens
+fn fail(message: String) -> ! {
+    eprintln!("{}", message);
+    std::process::exit(1);
+}
+
+// the settings file with the options of the command line on top
+fn load_settings(options: &cli::Options) -> Settings {
+    let mut settings = Settings::load(&options.settings)
+        .unwrap_or_else(|e| fail(format!("could not read the settings {}: {}", options.settings.display(), e)));
+    options.override_settings(&mut settings);
+    settings
+}
+
+// the world of a single player game, an existing world directory keeps its own seed
+fn world_options(options: &cli::Options, settings: &Settings) -> chunk::WorldOptions {
+    let mut meta = WorldMeta {seed: options.seed, sea_level: 44};
+    let save = options.world.as_ref().map(|directory| {
+        let save = WorldSave::open(directory)
+            .unwrap_or_else(|e| fail(format!("could not open the world {}: {}", directory.display(), e)));
+        match save.load_meta() {
+            Ok(Some(existing)) => meta = existing,
+            Ok(None) => save.save_meta(&meta)
+                .unwrap_or_else(|e| fail(format!("could not create the world {}: {}", directory.display(), e))),
+            Err(e) => fail(format!("could not read the world {}: {}", directory.display(), e)),
+        }
+        save
+    });
+    chunk::WorldOptions {seed: meta.seed, sea_level: meta.sea_level, view_distance: settings.view_distance, save}
+}
+
+// `--headless`, the same as the `server` binary but with the world options of the command line
+fn run_server(options: &cli::Options, settings: &Settings) -> ! {
+    let (models, warnings) = voxel::custom_model::load_models(std::path::Path::new("assets"), &voxel::atlas::Tiles::default());
+    for warning in warnings {
+        eprintln!("{}", warning);
+    }
+    let settings = voxel::server::ServerSettings {
+        seed: options.seed,
+        view_distance: settings.view_distance,
+        custom_blocks: models.models.len(),
+        world: options.world.clone(),
+        ..Default::default()
+    };
+
+    let server = voxel::server::Server::bind(&options.listen, settings)
+        .unwrap_or_else(|e| fail(format!("could not listen on {}: {}", options.listen, e)));
+    println!("listening on {}", options.listen);
+    match server.run() {
+        Ok(()) => std::process::exit(0),
+        Err(e) => fail(e.to_string()),
+    }
+}
+
 #[bevy_main]
 fn main() {
-    App::build()
+    let options = cli::Options::parse();
+    let settings = load_settings(&options);
+    if options.headless {
+        run_server(&options, &settings);
+    }
+
+    let network = options.connect.as_ref().map(|address| match voxel::client::Connection::connect(address, &options.name) {
+        Ok(connection) => network::Network::new(connection),
+        Err(e) => fail(format!("could not join {}: {}", address, e)),
+    });
+
+    let mut app = App::build();
+    app
+        // read by the window plugin, so it has to be there before it
+        .insert_resource(WindowDescriptor {
+            title: "Voxel!".to_string(),
+            width: settings.window[0] as f32,
+            height: settings.window[1] as f32,
+            vsync: settings.vsync,
+            ..Default::default()
+        })
         .add_plugins(DefaultPlugins)
 
         .add_plugin(NoCameraPlayerPlugin)
         .insert_resource(MovementSettings {
-            sensitivity: 0.025, // default: 0.00012
-            speed: 25.0, // default: 12.0
+            sensitivity: settings.sensitivity,
+            speed: settings.fly_speed,
         })
+        .insert_resource(menu::Controls::new(&settings.keys))
+        .insert_resource(world_options(&options, &settings))
+        .insert_resource(menu::Menu::new(options.settings.clone()))
+        .insert_resource(settings)
 
+        .add_asset::<render::ChunkMaterial>()
+        // the block textures are needed by the materials and the custom models
+        .add_startup_system_to_stage(StartupStage::PreStartup, render::build_atlas.system())
         .add_startup_system(setup.system())
+        .add_startup_system(render::setup_chunk_rendering.system())
+        .add_startup_system(render::load_models.system())
         .add_startup_system(chunk::spawn_world.system())
+        .add_startup_system(menu::setup_menu.system())
 
-        .add_startup_stage("spawn", SystemStage::single(chunk::generate_spawn.system()))
+        .add_startup_stage("spawn", SystemStage::parallel())
 
         .add_startup_stage("render", SystemStage::single(chunk::render_chunk.system()))
 
-        .add_system(controll::build.system())
+        .add_system(menu::menu_input.system().label("menu"))
+        .add_system(menu::update_menu_text.system().after("menu"))
+        .add_system(menu::apply_settings.system().after("menu"))
+        .add_plugin(console::ConsolePlugin)
+
+        .add_plugin(bevy::diagnostic::FrameTimeDiagnosticsPlugin::default())
+        .init_resource::<debug_hud::DebugHud>()
+        .add_startup_system(debug_hud::setup_debug_hud.system())
+        .add_system(debug_hud::toggle_debug_hud.system().label("debug_hud"))
+        .add_system(debug_hud::update_debug_hud.system().after("debug_hud"))
+
+        .add_event::<chunk::BlockChanged>()
+        .add_system(controll::build.system().label("build"))
         .add_system(controll::movement.system()) // syncs light position to builder
         .add_system(controll::builder_movement.system())
 
-        .run();
+        .init_resource::<voxel::lod::LodSettings>()
+        .add_system(chunk::update_lod.system())
+
+        .init_resource::<daytime::TimeOfDay>()
+        .add_system(daytime::time_controls.system())
+        .add_system(daytime::advance_time.system())
+        .add_system(daytime::update_sky.system())
+
+        .add_system(render::animate_textures.system())
+
+        // relights changed blocks before their chunks get remeshed
+        .add_system_to_stage(CoreStage::PostUpdate, chunk::update_light.system().label("light"))
+        .add_system_to_stage(CoreStage::PostUpdate, chunk::send_block_changes.system().label("block_changes").after("light"))
+        .add_system_to_stage(CoreStage::PostUpdate, chunk::remesh_chunks.system().after("block_changes"))
+        .add_system_to_stage(CoreStage::PostUpdate, visibility::cull_chunks.system());
+
+    match network {
+        // the world, the fluids and falling blocks come from the server
+        Some(network) => {
+            app
+                .insert_resource(network)
+                .add_startup_system_to_stage("spawn", network::setup_network.system())
+                .add_system(network::receive_messages.system().before("build"))
+                .add_system(network::send_movement.system())
+                .add_system(network::move_avatars.system())
+                .add_system(network::update_name_tags.system())
+                .add_system_to_stage(CoreStage::PostUpdate, network::flush_connection.system());
+        }
+        None => {
+            app
+                .add_startup_system_to_stage("spawn", chunk::generate_spawn.system())
+                .add_system(chunk::extend_view_distance.system())
+                // the chunks of a server are not written into a local world
+                .add_system_to_stage(CoreStage::PostUpdate, chunk::save_chunks.system().after("block_changes"))
+
+                .init_resource::<voxel::fluid::FluidSimulation>()
+                .add_system(chunk::activate_fluids.system())
+                .add_system_set(
+                    SystemSet::new()
+                        .with_run_criteria(FixedTimestep::step(voxel::fluid::TICK))
+                        .with_system(chunk::simulate_fluids.system()),
+                )
+
+                .add_system(gravity::start_falling.system())
+                .add_system(gravity::fall.system());
+        }
+    }
+    app.run();
 }
 
 /// set up a simple 3D scene
@@ -45,11 +206,11 @@ fn setup(
     asset_server: Res<AssetServer>,
     mut meshes: ResMut<Assets<Mesh>>,
 ) {
-    // Window settings
-    commands.insert_resource(WindowDescriptor {title: "Voxel!".to_string(), width: 1200.0, height: 800.0, vsync: false, ..Default::default()});
-        
-    // Clear Color    
+    // Clear Color, follows the time of day from now on
     commands.insert_resource(ClearColor(Color::rgb(0.0, 0.0, 0.0)));
+
+    // draws the name tags of other players
+    commands.spawn_bundle(UiCameraBundle::default());
         
     // Light
     commands.spawn_bundle(LightBundle {
@@ -73,19 +234,6 @@ fn setup(
         .insert(Camera)
         .insert(FlyCam);
 
-    let block_texture_handle = asset_server.load("textures/blocks.png");
-
-    let blocks = materials.add(StandardMaterial { 
-        base_color: Color::rgba(1.0, 1.0, 1.0, 1.0), 
-        base_color_texture: Some(block_texture_handle.clone()), 
-        roughness: 2.5,
-        metallic: 0.1,
-        ..Default::default() });
-
-    commands.insert_resource(Materials {
-        blocks: blocks,
-    });
-
     // spawn builderindicator
     let builder_texture_handle = asset_server.load("textures/builder.png");
 
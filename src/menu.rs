@@ -0,0 +1,266 @@
+use std::path::PathBuf;
+
+use bevy::prelude::*;
+use bevy::render::camera::PerspectiveProjection;
+
+use voxel::settings::{Action, KeyBindings, Settings, MAX_FOV, MAX_VIEW_DISTANCE, MIN_FLY_SPEED, MIN_FOV, MIN_SENSITIVITY, MIN_VIEW_DISTANCE};
+
+use crate::player_input::MovementSettings;
+
+// the keys that can be bound, the settings name them like bevy does
+const KEYS: [KeyCode; 54] = [
+    KeyCode::A, KeyCode::B, KeyCode::C, KeyCode::D, KeyCode::E, KeyCode::F, KeyCode::G,
+    KeyCode::H, KeyCode::I, KeyCode::J, KeyCode::K, KeyCode::L, KeyCode::M, KeyCode::N,
+    KeyCode::O, KeyCode::P, KeyCode::Q, KeyCode::R, KeyCode::S, KeyCode::T, KeyCode::U,
+    KeyCode::V, KeyCode::W, KeyCode::X, KeyCode::Y, KeyCode::Z,
+    KeyCode::Key0, KeyCode::Key1, KeyCode::Key2, KeyCode::Key3, KeyCode::Key4,
+    KeyCode::Key5, KeyCode::Key6, KeyCode::Key7, KeyCode::Key8, KeyCode::Key9,
+    KeyCode::Up, KeyCode::Down, KeyCode::Left, KeyCode::Right,
+    KeyCode::Space, KeyCode::Tab, KeyCode::Return, KeyCode::Back,
+    KeyCode::LShift, KeyCode::RShift, KeyCode::LControl, KeyCode::RControl,
+    KeyCode::LAlt, KeyCode::RAlt, KeyCode::Insert, KeyCode::Delete, KeyCode::Home, KeyCode::End,
+];
+
+// the window sizes the menu switches between
+const WINDOW_SIZES: [[u32; 2]; 5] = [[800, 600], [1200, 800], [1280, 720], [1600, 900], [1920, 1080]];
+
+fn key_code(name: &str) -> Option<KeyCode> {
+    KEYS.iter().copied().find(|key| format!("{:?}", key) == name)
+}
+
+/// the key of every `Action`, unknown names in the settings fall back to the default keys
+pub struct Controls {
+    keys: [KeyCode; 6],
+}
+impl Controls {
+    pub fn new(bindings: &KeyBindings) -> Self {
+        let defaults = KeyBindings::default();
+        let mut keys = [KeyCode::W; 6];
+        for (index, action) in Action::ALL.iter().enumerate() {
+            let name = bindings.get(*action);
+            keys[index] = key_code(name).unwrap_or_else(|| {
+                warn!("{} is not a key, {} stays on {}", name, action.name(), defaults.get(*action));
+                key_code(defaults.get(*action)).unwrap()
+            });
+        }
+        Controls {keys}
+    }
+
+    pub fn key(&self, action: Action) -> KeyCode {
+        self.keys[action as usize]
+    }
+}
+
+// the rows of the menu
+#[derive(Clone, Copy, PartialEq)]
+enum Entry {
+    ViewDistance,
+    Fov,
+    Sensitivity,
+    FlySpeed,
+    Vsync,
+    Window,
+    Key(Action),
+}
+
+const ENTRIES: [Entry; 12] = [
+    Entry::ViewDistance,
+    Entry::Fov,
+    Entry::Sensitivity,
+    Entry::FlySpeed,
+    Entry::Vsync,
+    Entry::Window,
+    Entry::Key(Action::Forward),
+    Entry::Key(Action::Back),
+    Entry::Key(Action::Left),
+    Entry::Key(Action::Right),
+    Entry::Key(Action::Up),
+    Entry::Key(Action::Down),
+];
+
+pub struct Menu {
+    pub open: bool,
+    // where the settings are saved when the menu closes
+    pub path: PathBuf,
+    selected: usize,
+    // waiting for the key that gets bound to the selected action
+    rebinding: bool,
+}
+impl Menu {
+    pub fn new(path: PathBuf) -> Self {
+        Menu {open: false, path, selected: 0, rebinding: false}
+    }
+
+    fn describe(&self, settings: &Settings) -> String {
+        let mut text = String::from("settings, arrows change them, Enter binds a key, Escape saves\n\n");
+        for (index, entry) in ENTRIES.iter().enumerate() {
+            let value = match entry {
+                Entry::ViewDistance => format!("view distance: {} chunks", settings.view_distance),
+                Entry::Fov => format!("field of view: {}", settings.fov),
+                Entry::Sensitivity => format!("mouse sensitivity: {:.3}", settings.sensitivity),
+                Entry::FlySpeed => format!("fly speed: {}", settings.fly_speed),
+                Entry::Vsync => format!("vsync: {}", if settings.vsync { "on" } else { "off" }),
+                Entry::Window => format!("window: {}x{}", settings.window[0], settings.window[1]),
+                Entry::Key(action) if self.rebinding && index == self.selected => format!("{}: press a key", action.name()),
+                Entry::Key(action) => format!("{}: {}", action.name(), settings.keys.get(*action)),
+            };
+            let marker = if index == self.selected { "> " } else { "  " };
+            text.push_str(marker);
+            text.push_str(&value);
+            text.push('\n');
+        }
+        text
+    }
+}
+
+pub struct MenuText;
+
+pub fn setup_menu(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands
+        .spawn_bundle(TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {left: Val::Px(40.0), top: Val::Px(40.0), ..Default::default()},
+                ..Default::default()
+            },
+            text: Text::with_section(
+                String::new(),
+                TextStyle {font: asset_server.load("fonts/DejaVuSansMono.ttf"), font_size: 22.0, color: Color::WHITE},
+                Default::default(),
+            ),
+            visible: Visible {is_visible: false, is_transparent: true},
+            ..Default::default()
+        })
+        .insert(MenuText);
+}
+
+/// Escape opens the menu and closes it again, closing saves the settings
+pub fn menu_input(
+    keys: Res<Input<KeyCode>>,
+    mut menu: ResMut<Menu>,
+    mut settings: ResMut<Settings>,
+    mut windows: ResMut<Windows>,
+    console: Res<crate::console::Console>,
+) {
+    // Escape closes the console first
+    if console.open {
+        return;
+    }
+    if menu.rebinding {
+        if keys.just_pressed(KeyCode::Escape) {
+            menu.rebinding = false;
+        } else if let Some(key) = keys.get_just_pressed().find(|key| KEYS.contains(key)) {
+            if let Entry::Key(action) = ENTRIES[menu.selected] {
+                *settings.keys.get_mut(action) = format!("{:?}", key);
+            }
+            menu.rebinding = false;
+        }
+        return;
+    }
+
+    if keys.just_pressed(KeyCode::Escape) {
+        menu.open = !menu.open;
+        let window = windows.get_primary_mut().unwrap();
+        window.set_cursor_lock_mode(!menu.open);
+        window.set_cursor_visibility(menu.open);
+
+        if !menu.open {
+            if let Err(e) = settings.save(&menu.path) {
+                error!("could not save the settings to {}: {}", menu.path.display(), e);
+            }
+        }
+        return;
+    }
+    if !menu.open {
+        return;
+    }
+
+    if keys.just_pressed(KeyCode::Up) {
+        menu.selected = (menu.selected + ENTRIES.len() - 1) % ENTRIES.len();
+    }
+    if keys.just_pressed(KeyCode::Down) {
+        menu.selected = (menu.selected + 1) % ENTRIES.len();
+    }
+
+    let step = match (keys.just_pressed(KeyCode::Left), keys.just_pressed(KeyCode::Right)) {
+        (true, false) => -1,
+        (false, true) => 1,
+        _ => 0,
+    };
+    let entry = ENTRIES[menu.selected];
+
+    if let Entry::Key(_) = entry {
+        if keys.just_pressed(KeyCode::Return) {
+            menu.rebinding = true;
+        }
+        return;
+    }
+    // Enter changes the entry like the right arrow
+    let step = match step {
+        0 if keys.just_pressed(KeyCode::Return) => 1,
+        0 => return,
+        step => step,
+    };
+
+    match entry {
+        Entry::ViewDistance => settings.view_distance = (settings.view_distance + step).clamp(MIN_VIEW_DISTANCE, MAX_VIEW_DISTANCE),
+        Entry::Fov => settings.fov = (settings.fov + step as f32 * 5.0).clamp(MIN_FOV, MAX_FOV),
+        Entry::Sensitivity => settings.sensitivity = (settings.sensitivity + step as f32 * 0.005).max(MIN_SENSITIVITY),
+        Entry::FlySpeed => settings.fly_speed = (settings.fly_speed + step as f32 * 5.0).max(MIN_FLY_SPEED),
+        Entry::Vsync => settings.vsync = !settings.vsync,
+        Entry::Window => {
+            let current = WINDOW_SIZES.iter().position(|size| *size == settings.window).unwrap_or(1);
+            let next = (current as i32 + step).rem_euclid(WINDOW_SIZES.len() as i32);
+            settings.window = WINDOW_SIZES[next as usize];
+        }
+        Entry::Key(_) => {}
+    }
+}
+
+/// shows the menu while it is open
+pub fn update_menu_text(
+    menu: Res<Menu>,
+    settings: Res<Settings>,
+    mut text: Query<(&mut Text, &mut Visible), With<MenuText>>,
+) {
+    if !menu.is_changed() && !settings.is_changed() {
+        return;
+    }
+    for (mut text, mut visible) in text.iter_mut() {
+        visible.is_visible = menu.open;
+        text.sections[0].value = menu.describe(&settings);
+    }
+}
+
+/// passes changed settings on to the camera, the window and the movement,
+/// the window is only touched when its own settings change so that resizing it by hand sticks
+pub fn apply_settings(
+    settings: Res<Settings>,
+    mut applied: Local<Option<Settings>>,
+    mut movement: ResMut<MovementSettings>,
+    mut controls: ResMut<Controls>,
+    mut windows: ResMut<Windows>,
+    mut camera: Query<&mut PerspectiveProjection, With<crate::Camera>>,
+) {
+    if !settings.is_changed() {
+        return;
+    }
+    movement.sensitivity = settings.sensitivity;
+    movement.speed = settings.fly_speed;
+    *controls = Controls::new(&settings.keys);
+
+    // the window starts out with the settings, see `main`
+    let previous = applied.get_or_insert_with(|| settings.clone());
+    let window = windows.get_primary_mut().unwrap();
+    if previous.vsync != settings.vsync {
+        window.set_vsync(settings.vsync);
+    }
+    if previous.window != settings.window {
+        window.set_resolution(settings.window[0] as f32, settings.window[1] as f32);
+    }
+    *previous = settings.clone();
+
+    for mut projection in camera.iter_mut() {
+        projection.fov = settings.fov.to_radians();
+    }
+}
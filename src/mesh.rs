@@ -0,0 +1,418 @@
+use crate::atlas::Tiles;
+use crate::block::{self, Block, BlockId};
+use crate::custom_model::CustomModels;
+use crate::fluid;
+use crate::light;
+use crate::model;
+use crate::world::{Chunk, World};
+
+/// vertices in the format of `pack_vertex` and the indices of the triangles
+#[derive(Default)]
+pub struct MeshBuffers {
+    pub vertices: Vec<[u32; 3]>,
+    pub indices: Vec<u32>,
+}
+
+// positions are stored in 16th of a block and moved by this many blocks, so that
+// translucent meshes can be centered on their chunk, see `chunk::spawn_chunk_mesh`
+const POSITION_BIAS: f32 = 16.0;
+
+/// a vertex of a chunk mesh before it gets packed
+#[derive(Clone, Debug, PartialEq)]
+pub struct Vertex {
+    // position inside of the chunk
+    pub position: [f32; 3],
+    // index into `FACES` of the direction the vertex faces
+    pub face: usize,
+    pub tile: u16,
+    // position inside of the tile, from 0.0 to 1.0
+    pub uv: [f32; 2],
+    // a `Chunk::light` value
+    pub light: u8,
+    // ambient occlusion from 0 for a fully occluded corner to 3 for an open one
+    pub ao: u8,
+    // number of frames and frames per second, see `block::animation`
+    pub animation: (u8, f32),
+}
+
+/// packs a vertex into three words: the position and ambient occlusion,
+/// the texture and face and the light and animation, see `chunk.vert`
+pub fn pack_vertex(vertex: &Vertex) -> [u32; 3] {
+    let position = |axis: usize| (((vertex.position[axis] + POSITION_BIAS) * 16.0).round() as u32).min(1023);
+    let uv = |axis: usize| ((vertex.uv[axis] * 16.0).round() as u32).min(16);
+    let (frames, frames_per_second) = vertex.animation;
+
+    [
+        position(0) | position(1) << 10 | position(2) << 20 | (vertex.ao.min(3) as u32) << 30,
+        (vertex.tile as u32).min(1023) | uv(0) << 10 | uv(1) << 15 | (vertex.face as u32) << 20,
+        vertex.light as u32 | (frames as u32) << 8 | ((frames_per_second * 4.0).round() as u32).min(255) << 16,
+    ]
+}
+
+/// reads a vertex back from the format of `pack_vertex`, positions and uvs are rounded to 16th
+pub fn unpack_vertex(packed: [u32; 3]) -> Vertex {
+    let position = |axis: usize| ((packed[0] >> (axis * 10)) & 1023) as f32 / 16.0 - POSITION_BIAS;
+    let uv = |shift: usize| ((packed[1] >> shift) & 31) as f32 / 16.0;
+
+    Vertex {
+        position: [position(0), position(1), position(2)],
+        face: ((packed[1] >> 20) & 7) as usize,
+        tile: (packed[1] & 1023) as u16,
+        uv: [uv(10), uv(15)],
+        light: (packed[2] & 255) as u8,
+        ao: (packed[0] >> 30) as u8,
+        animation: (((packed[2] >> 8) & 255) as u8, ((packed[2] >> 16) & 255) as f32 / 4.0),
+    }
+}
+
+/// moves a packed vertex by whole blocks
+pub fn translate_vertex(vertex: [u32; 3], offset: [i32; 3]) -> [u32; 3] {
+    let mut position = vertex[0];
+    for (axis, offset) in offset.iter().enumerate() {
+        let shift = axis * 10;
+        let moved = (((position >> shift) & 1023) as i32 + offset * 16).clamp(0, 1023) as u32;
+        position = (position & !(1023 << shift)) | moved << shift;
+    }
+    [position, vertex[1], vertex[2]]
+}
+
+/// mesh of a single block that is not part of a chunk
+pub fn create_block_mesh(block: BlockId, tiles: &Tiles) -> MeshBuffers {
+    let mut vertices: Vec<[u32; 3]> = Vec::with_capacity(24);
+    let mut indices: Vec<u32> = Vec::with_capacity(36);
+
+    let tile = tiles.tile(block::texture(block));
+
+    for (face_index, face) in FACES.iter().enumerate() {
+        let vertices_len = vertices.len() as u32;
+        for (i, corner) in face.corners.iter().enumerate() {
+            vertices.push(pack_vertex(&Vertex {
+                position: *corner,
+                face: face_index,
+                tile,
+                uv: [(i % 2) as f32, (i / 2) as f32],
+                light: light::SKY,
+                ao: 3,
+                animation: block::animation(block),
+            }));
+        }
+
+        for index in face.indices.iter() {
+            indices.push(vertices_len + index);
+        }
+    }
+
+    MeshBuffers {vertices, indices}
+}
+
+/// the render paths a chunk gets split into
+#[derive(Clone, Copy, PartialEq)]
+pub enum MeshPass {
+    Opaque,
+    // blocks with fully transparent texels, like leaves and plants
+    Cutout,
+    // blocks that are blended with what is behind them, like water and glass
+    Translucent,
+}
+
+/// the render path a block is drawn in
+pub fn block_pass(block: BlockId) -> MeshPass {
+    if block::is_translucent(block) {
+        MeshPass::Translucent
+    } else if block::is_cutout(block) {
+        MeshPass::Cutout
+    } else {
+        MeshPass::Opaque
+    }
+}
+
+pub struct Face {
+    // direction the face points to
+    pub normal: [i32; 3],
+    pub corners: [[f32; 3]; 4],
+    pub indices: [u32; 6],
+}
+
+pub const FACES: [Face; 6] = [
+    //below plane
+    Face {
+        normal: [0, -1, 0],
+        corners: [[0.0, 0.0, 0.0], [0.0, 0.0, 1.0], [1.0, 0.0, 0.0], [1.0, 0.0, 1.0]],
+        indices: [0, 2, 1, 3, 1, 2],
+    },
+    //above plane
+    Face {
+        normal: [0, 1, 0],
+        corners: [[0.0, 1.0, 0.0], [0.0, 1.0, 1.0], [1.0, 1.0, 0.0], [1.0, 1.0, 1.0]],
+        indices: [2, 0, 3, 0, 1, 3],
+    },
+    //left plane
+    Face {
+        normal: [0, 0, -1],
+        corners: [[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [1.0, 1.0, 0.0]],
+        indices: [1, 0, 3, 0, 2, 3],
+    },
+    //right plane
+    Face {
+        normal: [0, 0, 1],
+        corners: [[0.0, 0.0, 1.0], [1.0, 0.0, 1.0], [0.0, 1.0, 1.0], [1.0, 1.0, 1.0]],
+        indices: [0, 1, 3, 2, 0, 3],
+    },
+    //front plane
+    Face {
+        normal: [-1, 0, 0],
+        corners: [[0.0, 0.0, 0.0], [0.0, 0.0, 1.0], [0.0, 1.0, 0.0], [0.0, 1.0, 1.0]],
+        indices: [0, 1, 2, 2, 1, 3],
+    },
+    //back plane
+    Face {
+        normal: [1, 0, 0],
+        corners: [[1.0, 0.0, 0.0], [1.0, 0.0, 1.0], [1.0, 1.0, 0.0], [1.0, 1.0, 1.0]],
+        indices: [1, 0, 2, 1, 2, 3],
+    },
+];
+
+// wether a face of `block` towards `neighbour` is visible in the given render path
+pub fn face_visible(block: BlockId, neighbour: BlockId, pass: MeshPass) -> bool {
+    block_pass(block) == pass && !block::is_opaque(neighbour) && neighbour != block
+}
+
+// looks up the chunk and the position inside of it next to a face, `None` if that chunk is not loaded
+fn neighbour_position<'a>(
+    chunk: &'a Chunk,
+    neighbours: &[Option<&'a Chunk>],
+    face: usize,
+    position: [usize; 3],
+) -> Option<(&'a Chunk, [usize; 3])> {
+    let nx = position[0] as i32 + FACES[face].normal[0];
+    let ny = position[1] as i32 + FACES[face].normal[1];
+    let nz = position[2] as i32 + FACES[face].normal[2];
+
+    if (0..32).contains(&nx) && (0..32).contains(&ny) && (0..32).contains(&nz) {
+        Some((chunk, [nx as usize, ny as usize, nz as usize]))
+    } else {
+        neighbours[face].map(|neighbour_chunk| {
+            (neighbour_chunk, [nx.rem_euclid(32) as usize, ny.rem_euclid(32) as usize, nz.rem_euclid(32) as usize])
+        })
+    }
+}
+
+// faces towards unloaded chunks are always drawn
+fn neighbour_block(
+    chunk: &Chunk,
+    neighbours: &[Option<&Chunk>],
+    face: usize,
+    position: [usize; 3],
+) -> Block {
+    neighbour_position(chunk, neighbours, face, position)
+        .map(|(chunk, position)| chunk.get(position))
+        .unwrap_or(Block::new(block::AIR))
+}
+
+// a face is lit by the light of the block in front of it
+fn neighbour_light(
+    chunk: &Chunk,
+    neighbours: &[Option<&Chunk>],
+    face: usize,
+    position: [usize; 3],
+) -> u8 {
+    neighbour_position(chunk, neighbours, face, position)
+        .map(|(chunk, position)| chunk.light[position[0]][position[1]][position[2]])
+        .unwrap_or(light::SKY)
+}
+
+fn fluid_height(level: u8) -> f32 {
+    if level & fluid::FALLING != 0 {
+        1.0
+    } else {
+        (8 - level) as f32 / 9.0
+    }
+}
+
+// wether the block at a position relative to the chunk hides the corners next to it, blocks outside of the chunk are looked up in the world
+fn occludes(chunk: &Chunk, world: &World, position: [i32; 3]) -> bool {
+    let block = if position.iter().all(|axis| (0..32).contains(axis)) {
+        Some(chunk.get([position[0] as usize, position[1] as usize, position[2] as usize]))
+    } else {
+        world.get([chunk.x * 32 + position[0], chunk.y * 32 + position[1], chunk.z * 32 + position[2]])
+    };
+    block.map(|block| block::is_opaque(block.id)).unwrap_or(false)
+}
+
+// ambient occlusion of a corner of a face, from the two blocks along its edges and the one diagonal to it in front of the face
+fn corner_ao(chunk: &Chunk, world: &World, position: [usize; 3], face: usize, corner: [f32; 3]) -> u8 {
+    let normal = FACES[face].normal;
+    let [u, v] = model::face_axes(face);
+
+    let mut front = [position[0] as i32 + normal[0], position[1] as i32 + normal[1], position[2] as i32 + normal[2]];
+    let mut side_u = front;
+    side_u[u] += if corner[u] > 0.5 { 1 } else { -1 };
+    let mut side_v = front;
+    side_v[v] += if corner[v] > 0.5 { 1 } else { -1 };
+    front[u] = side_u[u];
+    front[v] = side_v[v];
+
+    let side_u = occludes(chunk, world, side_u);
+    let side_v = occludes(chunk, world, side_v);
+    if side_u && side_v {
+        return 0;
+    }
+    3 - side_u as u8 - side_v as u8 - occludes(chunk, world, front) as u8
+}
+
+pub fn create_chunk_mesh(
+    chunk: usize,
+    world: &World,
+    pass: MeshPass,
+    models: &CustomModels,
+    tiles: &Tiles,
+) -> MeshBuffers {
+    let v_length = 8*32*32*32;
+
+    let mut vertices: Vec<[u32; 3]> = Vec::with_capacity(v_length);
+    let mut indices: Vec<u32> = Vec::with_capacity(v_length);
+
+    let chunk = &world.chunk_index[chunk];
+
+    // neighbouring chunks in the order of `FACES`, used to look up light across the chunk border
+    let neighbours: Vec<Option<&Chunk>> = FACES
+        .iter()
+        .map(|face| world.get_chunk([chunk.x + face.normal[0], chunk.y + face.normal[1], chunk.z + face.normal[2]]))
+        .collect();
+
+    // faces towards chunks of another level of detail are always drawn to close the seam, see `lod`
+    let block_neighbours: Vec<Option<&Chunk>> = neighbours
+        .iter()
+        .map(|neighbour| neighbour.filter(|neighbour| neighbour.lod == chunk.lod))
+        .collect();
+
+    for x1 in 0..32 {
+        for y1 in 0..32 {
+            for z1 in 0..32 {
+
+                let x: f32 = x1 as f32;
+                let y: f32 = y1 as f32;
+                let z: f32= z1 as f32;
+
+                let Block { id: block, state } = chunk.get([x1, y1, z1]);
+
+                if block == block::AIR || block_pass(block) != pass {
+                    continue;
+                }
+
+                let tile = tiles.tile(block::texture(block));
+                let animation = block::animation(block);
+
+                // plants are lit by their own block and look the same from both sides
+                if block::shape(block) == block::Shape::Cross {
+                    for quad in model::CROSS.iter() {
+                        let vertices_len = vertices.len() as u32;
+                        for (i, corner) in quad.iter().enumerate() {
+                            vertices.push(pack_vertex(&Vertex {
+                                position: [ x + corner[0], y + corner[1], z + corner[2] ],
+                                face: 1,
+                                tile,
+                                uv: [(i % 2) as f32, (i / 2) as f32],
+                                light: chunk.light[x1][y1][z1],
+                                ao: 3,
+                                animation,
+                            }));
+                        }
+                        for index in [0, 1, 2, 2, 1, 3, 0, 2, 1, 2, 3, 1].iter() {
+                            indices.push(vertices_len + index);
+                        }
+                    }
+                    continue;
+                }
+
+                // models from the assets are moved to the position of the block
+                if block::shape(block) == block::Shape::Custom {
+                    if let Some(model) = models.get(block) {
+                        let vertices_len = vertices.len() as u32;
+
+                        for (i, position) in model.positions.iter().enumerate() {
+                            vertices.push(pack_vertex(&Vertex {
+                                position: [ x + position[0], y + position[1], z + position[2] ],
+                                face: model.faces[i],
+                                tile: model.tiles[i],
+                                uv: model.uvs[i],
+                                light: chunk.light[x1][y1][z1],
+                                ao: 3,
+                                animation,
+                            }));
+                        }
+                        indices.extend(model.indices.iter().map(|index| vertices_len + index));
+                    }
+                    continue;
+                }
+
+                // fluid surfaces sink with the distance to their source
+                let mut height: f32 = 1.0;
+                if block::is_fluid(block) && neighbour_block(chunk, &block_neighbours, 1, [x1, y1, z1]).id != block {
+                    height = fluid_height(state);
+                }
+
+                let mut connections = [false; 4];
+                for (side, face) in model::SIDES.iter().enumerate() {
+                    connections[side] = model::fence_connects(neighbour_block(chunk, &block_neighbours, *face, [x1, y1, z1]).id);
+                }
+
+                for mut cuboid in model::cuboids(Block { id: block, state }, connections) {
+                    cuboid.max[1] = cuboid.max[1].min(height);
+
+                    for (face_index, face) in FACES.iter().enumerate() {
+                        let axis = (0..3).find(|axis| face.normal[*axis] != 0).unwrap();
+                        let on_border = if face.normal[axis] > 0 { cuboid.max[axis] == 1.0 } else { cuboid.min[axis] == 0.0 };
+
+                        // faces inside of the block, like sunken fluid surfaces, are lit by the block itself
+                        let light = if on_border {
+                            let neighbour = neighbour_block(chunk, &block_neighbours, face_index, [x1, y1, z1]);
+
+                            if neighbour.id == block && block::shape(block) == block::Shape::Cube {
+                                continue;
+                            }
+                            let quarters = model::quarters(face_index, cuboid.min, cuboid.max);
+                            if quarters & !model::coverage(neighbour, face_index ^ 1) == 0 {
+                                continue;
+                            }
+
+                            neighbour_light(chunk, &neighbours, face_index, [x1, y1, z1])
+                        } else {
+                            chunk.light[x1][y1][z1]
+                        };
+
+                        // only full blocks are darkened in their corners, smaller shapes do not reach them
+                        let shaded = on_border && block::is_opaque(block);
+
+                        let [u, v] = model::face_axes(face_index);
+
+                        // creates vertices
+                        let vertices_len = vertices.len() as u32;
+                        for corner in face.corners.iter() {
+                            let mut position = [0.0; 3];
+                            for axis in 0..3 {
+                                position[axis] = cuboid.min[axis] + corner[axis] * (cuboid.max[axis] - cuboid.min[axis]);
+                            }
+                            vertices.push(pack_vertex(&Vertex {
+                                position: [ x + position[0], y + position[1], z + position[2] ],
+                                face: face_index,
+                                tile,
+                                uv: [position[u], position[v]],
+                                light,
+                                ao: if shaded { corner_ao(chunk, world, [x1, y1, z1], face_index, *corner) } else { 3 },
+                                animation,
+                            }));
+                        }
+
+                        // creates indices
+                        for index in face.indices.iter() {
+                            indices.push(vertices_len + index);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    MeshBuffers {vertices, indices}
+}
@@ -0,0 +1,170 @@
+use crate::block::{self, Block, BlockId, Shape};
+use crate::mesh::FACES;
+
+// block state of slabs and stairs: the lowest two bits are the facing of stairs
+// (x-, x+, z-, z+), the third bit puts slabs and stairs into the upper half of the block
+pub const FACING: u8 = 0b011;
+pub const TOP: u8 = 0b100;
+
+/// an axis aligned box inside of a block, from 0.0 to 1.0 on every axis
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Cuboid {
+    pub min: [f32; 3],
+    pub max: [f32; 3],
+}
+impl Cuboid {
+    pub const fn new(min: [f32; 3], max: [f32; 3]) -> Self {
+        Cuboid {min, max}
+    }
+
+    // mirrors the cuboid vertically
+    fn flip(self) -> Self {
+        Cuboid::new(
+            [self.min[0], 1.0 - self.max[1], self.min[2]],
+            [self.max[0], 1.0 - self.min[1], self.max[2]],
+        )
+    }
+}
+
+const FULL: Cuboid = Cuboid::new([0.0, 0.0, 0.0], [1.0, 1.0, 1.0]);
+const LOWER_HALF: Cuboid = Cuboid::new([0.0, 0.0, 0.0], [1.0, 0.5, 1.0]);
+
+// upper quarter of stairs, in the order of the facings
+const STAIRS_STEP: [Cuboid; 4] = [
+    Cuboid::new([0.0, 0.5, 0.0], [0.5, 1.0, 1.0]),
+    Cuboid::new([0.5, 0.5, 0.0], [1.0, 1.0, 1.0]),
+    Cuboid::new([0.0, 0.5, 0.0], [1.0, 1.0, 0.5]),
+    Cuboid::new([0.0, 0.5, 0.5], [1.0, 1.0, 1.0]),
+];
+
+const FENCE_POST: Cuboid = Cuboid::new([0.375, 0.0, 0.375], [0.625, 1.0, 0.625]);
+// heights of the two rails between fence posts
+const FENCE_RAILS: [[f32; 2]; 2] = [[0.375, 0.5625], [0.75, 0.9375]];
+
+// the horizontal sides of a block in the order of the stairs facings, as index into `FACES`
+pub const SIDES: [usize; 4] = [4, 5, 2, 3];
+
+/// wether a fence connects to the block next to it
+pub fn fence_connects(neighbour: BlockId) -> bool {
+    neighbour == block::FENCE || block::is_opaque(neighbour)
+}
+
+/// cuboids that make up a block, `connections` are the sides in the order of `SIDES` a fence connects to
+pub fn cuboids(block: Block, connections: [bool; 4]) -> Vec<Cuboid> {
+    let top = block.state & TOP != 0;
+    match block::shape(block.id) {
+        Shape::Empty | Shape::Cross | Shape::Custom => Vec::new(),
+        Shape::Cube => vec![FULL],
+        Shape::Slab => {
+            if top { vec![LOWER_HALF.flip()] } else { vec![LOWER_HALF] }
+        }
+        Shape::Stairs => {
+            let step = STAIRS_STEP[(block.state & FACING) as usize];
+            if top { vec![LOWER_HALF.flip(), step.flip()] } else { vec![LOWER_HALF, step] }
+        }
+        Shape::Fence => {
+            let mut cuboids = vec![FENCE_POST];
+            for (side, connected) in connections.iter().enumerate() {
+                if !connected {
+                    continue;
+                }
+                for rail in FENCE_RAILS.iter() {
+                    let (from, to) = if side % 2 == 0 { (0.0, 0.4375) } else { (0.5625, 1.0) };
+                    cuboids.push(if side < 2 {
+                        Cuboid::new([from, rail[0], 0.4375], [to, rail[1], 0.5625])
+                    } else {
+                        Cuboid::new([0.4375, rail[0], from], [0.5625, rail[1], to])
+                    });
+                }
+            }
+            cuboids
+        }
+    }
+}
+
+// axes of the plane of a face, the first one is the u and the second one the v axis of its texture
+pub fn face_axes(face: usize) -> [usize; 2] {
+    let corners = &FACES[face].corners;
+    let axis = |corner: &[f32; 3]| (0..3).find(|axis| corner[*axis] != corners[0][*axis]).unwrap();
+    [axis(&corners[1]), axis(&corners[2])]
+}
+
+/// index into `FACES` of the direction that is the closest to a normal
+pub fn nearest_face(normal: [f32; 3]) -> usize {
+    let alignment = |face: usize| {
+        let face = FACES[face].normal;
+        face[0] as f32 * normal[0] + face[1] as f32 * normal[1] + face[2] as f32 * normal[2]
+    };
+    (0..FACES.len()).fold(0, |nearest, face| if alignment(face) > alignment(nearest) { face } else { nearest })
+}
+
+/// the quarters of a side of the block a rectangle on it overlaps,
+/// bit `u + 2 * v` stands for the quarter at `u` and `v` along `face_axes`
+pub fn quarters(face: usize, min: [f32; 3], max: [f32; 3]) -> u8 {
+    let [u, v] = face_axes(face);
+    let mut quarters = 0;
+    for qu in 0..2 {
+        for qv in 0..2 {
+            let (lu, lv) = (qu as f32 * 0.5, qv as f32 * 0.5);
+            if min[u] < lu + 0.5 && max[u] > lu && min[v] < lv + 0.5 && max[v] > lv {
+                quarters |= 1 << (qu + 2 * qv);
+            }
+        }
+    }
+    quarters
+}
+
+/// the quarters of a side that are completely hidden behind the block, as in `quarters`
+pub fn coverage(block: Block, face: usize) -> u8 {
+    if block.id == block::AIR || block::is_translucent(block.id) || block::is_cutout(block.id) || block::is_fluid(block.id) {
+        return 0;
+    }
+
+    let normal = FACES[face].normal;
+    let axis = (0..3).find(|axis| normal[*axis] != 0).unwrap();
+    let border = if normal[axis] > 0 { 1.0 } else { 0.0 };
+    let [u, v] = face_axes(face);
+
+    let mut covered = 0;
+    for cuboid in cuboids(block, [false; 4]) {
+        let touches = if border == 1.0 { cuboid.max[axis] == 1.0 } else { cuboid.min[axis] == 0.0 };
+        if !touches {
+            continue;
+        }
+        for qu in 0..2 {
+            for qv in 0..2 {
+                let (lu, lv) = (qu as f32 * 0.5, qv as f32 * 0.5);
+                if cuboid.min[u] <= lu && cuboid.max[u] >= lu + 0.5 && cuboid.min[v] <= lv && cuboid.max[v] >= lv + 0.5 {
+                    covered |= 1 << (qu + 2 * qv);
+                }
+            }
+        }
+    }
+    covered
+}
+
+/// state of a block placed by someone looking into `direction`,
+/// stairs rise away from them and slabs go to the upper half when they look up
+pub fn placement_state(block: BlockId, direction: [f32; 3]) -> u8 {
+    let top = if direction[1] > 0.0 { TOP } else { 0 };
+    match block::shape(block) {
+        Shape::Slab => top,
+        Shape::Stairs => {
+            let facing = if direction[0].abs() > direction[2].abs() {
+                if direction[0] < 0.0 { 0 } else { 1 }
+            } else if direction[2] < 0.0 {
+                2
+            } else {
+                3
+            };
+            top | facing
+        }
+        _ => 0,
+    }
+}
+
+// corners of the two crossed quads of plants, the upper ones first so that the texture stands upright
+pub const CROSS: [[[f32; 3]; 4]; 2] = [
+    [[0.15, 1.0, 0.15], [0.85, 1.0, 0.85], [0.15, 0.0, 0.15], [0.85, 0.0, 0.85]],
+    [[0.15, 1.0, 0.85], [0.85, 1.0, 0.15], [0.15, 0.0, 0.85], [0.85, 0.0, 0.15]],
+];
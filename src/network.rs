@@ -0,0 +1,265 @@
+use bevy::app::AppExit;
+use bevy::prelude::*;
+
+use voxel::block::Block;
+use voxel::client::{Connection, Prediction};
+use voxel::protocol::{self, ClientMessage, ServerMessage};
+use voxel::world::World;
+
+use crate::chunk::ChunkMesh;
+
+// seconds between two updates of the camera transform sent to the server
+const MOVE_INTERVAL: f64 = 0.1;
+// how fast avatars catch up with the last position sent by the server
+const AVATAR_SMOOTHING: f32 = 10.0;
+// world units between the top of an avatar and its name tag
+const NAME_TAG_HEIGHT: f32 = 1.2;
+
+/// the connection to a server, the world is received from it instead of being generated, see `chunk::generate_spawn`
+pub struct Network {
+    pub connection: Connection,
+    pub prediction: Prediction,
+    last_move: f64,
+    last_transform: Option<Transform>,
+}
+impl Network {
+    pub fn new(connection: Connection) -> Self {
+        Network {connection, prediction: Prediction::default(), last_move: 0.0, last_transform: None}
+    }
+
+    /// applies an edit right away and sends it to the server, see `controll::build`,
+    /// returns false if nothing changed and nothing was sent
+    pub fn edit(&mut self, world: &mut World, position: [i32; 3], block: Block) -> bool {
+        if !self.prediction.predict(world, position, block) {
+            return false;
+        }
+        self.connection.send(&ClientMessage::SetBlock {position, block});
+        true
+    }
+}
+
+/// another player connected to the same server
+pub struct Avatar {
+    pub player: u32,
+    pub name: String,
+    // where the server last saw the player
+    pub target: Transform,
+}
+
+/// ui text with the name of an avatar, follows it on the screen
+pub struct NameTag {
+    pub player: u32,
+}
+
+pub struct AvatarAssets {
+    pub mesh: Handle<Mesh>,
+    pub material: Handle<StandardMaterial>,
+    pub font: Handle<Font>,
+}
+
+/// moves the camera to the spawn of the server and prepares the avatar meshes
+pub fn setup_network(
+    mut commands: Commands,
+    network: Res<Network>,
+    asset_server: Res<AssetServer>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut camera: Query<&mut Transform, With<crate::Camera>>,
+) {
+    let spawn = network.connection.welcome.spawn;
+    for mut camera in camera.iter_mut() {
+        camera.translation = Vec3::new(spawn[0], spawn[1], spawn[2]);
+    }
+
+    commands.insert_resource(AvatarAssets {
+        // about the size of a player, the camera sits at the top
+        mesh: meshes.add(Mesh::from(shape::Box::new(0.6, 1.8, 0.6))),
+        material: materials.add(Color::rgb(0.2, 0.45, 0.9).into()),
+        font: asset_server.load("fonts/DejaVuSansMono.ttf"),
+    });
+}
+
+/// puts the received chunks into the world and applies the changes and players sent by the server
+#[allow(clippy::too_many_arguments)]
+pub fn receive_messages(
+    mut commands: Commands,
+    mut network: ResMut<Network>,
+    assets: Res<AvatarAssets>,
+    mut world: Query<&mut World, With<World>>,
+    mut avatars: Query<(Entity, &mut Avatar)>,
+    name_tags: Query<(Entity, &NameTag)>,
+    chunk_meshes: Query<(Entity, &ChunkMesh)>,
+    mut app_exit: EventWriter<AppExit>,
+) {
+    let messages = network.connection.receive();
+    if network.connection.is_closed() {
+        error!("lost the connection to the server");
+        app_exit.send(AppExit);
+        return;
+    }
+
+    for mut world in world.iter_mut() {
+        let mut received: Vec<[i32; 3]> = Vec::new();
+
+        for message in messages.iter() {
+            match message {
+                ServerMessage::Chunk { position, blocks } => {
+                    if world.get_chunk(*position).is_some() {
+                        continue;
+                    }
+                    match protocol::decode_chunk(*position, blocks) {
+                        Ok(chunk) => {
+                            world.add_chunk(chunk);
+                            received.push(*position);
+                        }
+                        Err(e) => warn!("skipping the chunk at {:?}: {}", position, e),
+                    }
+                }
+                ServerMessage::ChunkUnloaded { position } => {
+                    world.remove_chunk(*position);
+                    received.retain(|chunk| chunk != position);
+                    // the faces of the neighbours towards it were hidden by its blocks
+                    for offset in [[0, -1, 0], [0, 1, 0], [0, 0, -1], [0, 0, 1], [-1, 0, 0], [1, 0, 0]].iter() {
+                        let neighbour = [position[0] + offset[0], position[1] + offset[1], position[2] + offset[2]];
+                        if let Some(chunk) = world.get_chunk_mut(neighbour) {
+                            chunk.dirty = true;
+                        }
+                    }
+                    for (entity, chunk_mesh) in chunk_meshes.iter() {
+                        if [chunk_mesh.x, chunk_mesh.y, chunk_mesh.z] == *position {
+                            commands.entity(entity).despawn();
+                        }
+                    }
+                }
+                ServerMessage::BlockChanged { position, block } => {
+                    network.prediction.confirm(&mut world, *position, *block);
+                }
+                ServerMessage::PlayerJoined { player, name } => {
+                    info!("{} joined the game", name);
+                    commands
+                        .spawn_bundle(PbrBundle {
+                            mesh: assets.mesh.clone(),
+                            material: assets.material.clone(),
+                            ..Default::default()
+                        })
+                        .insert(Avatar {player: *player, name: name.clone(), target: Transform::identity()});
+                    commands
+                        .spawn_bundle(TextBundle {
+                            style: Style {position_type: PositionType::Absolute, ..Default::default()},
+                            text: Text::with_section(
+                                name.clone(),
+                                TextStyle {font: assets.font.clone(), font_size: 18.0, color: Color::WHITE},
+                                Default::default(),
+                            ),
+                            visible: Visible {is_visible: false, is_transparent: true},
+                            ..Default::default()
+                        })
+                        .insert(NameTag {player: *player});
+                }
+                ServerMessage::PlayerMoved { player, position, rotation } => {
+                    for (_, mut avatar) in avatars.iter_mut() {
+                        if avatar.player == *player {
+                            // the camera is at the eyes, the avatar mesh is centered on the body
+                            avatar.target = Transform {
+                                translation: Vec3::new(position[0], position[1] - 0.7, position[2]),
+                                rotation: Quat::from_xyzw(rotation[0], rotation[1], rotation[2], rotation[3]),
+                                ..Default::default()
+                            };
+                        }
+                    }
+                }
+                ServerMessage::PlayerLeft { player } => {
+                    for (entity, avatar) in avatars.iter_mut() {
+                        if avatar.player == *player {
+                            info!("{} left the game", avatar.name);
+                            commands.entity(entity).despawn();
+                        }
+                    }
+                    for (entity, name_tag) in name_tags.iter() {
+                        if name_tag.player == *player {
+                            commands.entity(entity).despawn();
+                        }
+                    }
+                }
+                ServerMessage::Welcome { .. } | ServerMessage::Rejected { .. } => {}
+            }
+        }
+
+        // only the new chunks and their neighbours, the cost does not grow with the loaded world
+        if !received.is_empty() {
+            crate::chunk::relight(&mut world, &received);
+        }
+    }
+}
+
+/// sends the camera transform to the server a few times per second while it changes
+pub fn send_movement(
+    time: Res<Time>,
+    mut network: ResMut<Network>,
+    camera: Query<&Transform, With<crate::Camera>>,
+) {
+    if time.seconds_since_startup() - network.last_move < MOVE_INTERVAL {
+        return;
+    }
+    for camera in camera.iter() {
+        if network.last_transform == Some(*camera) {
+            continue;
+        }
+        network.last_move = time.seconds_since_startup();
+        network.last_transform = Some(*camera);
+
+        let (position, rotation) = (camera.translation, camera.rotation);
+        network.connection.send(&ClientMessage::Move {
+            position: [position.x, position.y, position.z],
+            rotation: [rotation.x, rotation.y, rotation.z, rotation.w],
+        });
+    }
+}
+
+/// sends everything that was queued during the frame
+pub fn flush_connection(mut network: ResMut<Network>) {
+    network.connection.flush();
+}
+
+/// moves the avatars smoothly towards the positions sent by the server, they only turn around the y axis
+pub fn move_avatars(time: Res<Time>, mut avatars: Query<(&Avatar, &mut Transform)>) {
+    let t = (time.delta_seconds() * AVATAR_SMOOTHING).min(1.0);
+    for (avatar, mut transform) in avatars.iter_mut() {
+        let forward = avatar.target.rotation * -Vec3::Z;
+        let yaw = Quat::from_rotation_y(forward.x.atan2(forward.z) + std::f32::consts::PI);
+
+        transform.translation = transform.translation.lerp(avatar.target.translation, t);
+        transform.rotation = transform.rotation.slerp(yaw, t);
+    }
+}
+
+/// keeps the name tags above the heads of their avatars, tags behind the camera are hidden
+pub fn update_name_tags(
+    windows: Res<Windows>,
+    camera: Query<(&bevy::render::camera::Camera, &GlobalTransform), With<crate::Camera>>,
+    avatars: Query<(&Avatar, &Transform)>,
+    mut name_tags: Query<(&NameTag, &mut Style, &mut Visible, &Node)>,
+) {
+    let (camera, camera_transform) = match camera.iter().next() {
+        Some(camera) => camera,
+        None => return,
+    };
+
+    for (name_tag, mut style, mut visible, node) in name_tags.iter_mut() {
+        let screen = avatars
+            .iter()
+            .find(|(avatar, _)| avatar.player == name_tag.player)
+            .and_then(|(_, transform)| {
+                camera.world_to_screen(&windows, camera_transform, transform.translation + Vec3::Y * NAME_TAG_HEIGHT)
+            });
+
+        match screen {
+            Some(screen) => {
+                visible.is_visible = true;
+                style.position.left = Val::Px(screen.x - node.size.x / 2.0);
+                style.position.bottom = Val::Px(screen.y);
+            }
+            None => visible.is_visible = false,
+        }
+    }
+}
@@ -5,6 +5,10 @@ use bevy::app::{Events, ManualEventReader};
 use bevy::input::mouse::MouseMotion;
 use bevy::prelude::*;
 
+use voxel::settings::Action;
+
+use crate::menu::Controls;
+
 fn unit_x() -> Vec3 {
     Vec3::new(1., 0., 0.)
 }
@@ -56,33 +60,40 @@ fn initial_grab_cursor(mut windows: ResMut<Windows>) {
 }
 
 
-/// Handles keyboard input and movement
+/// Handles keyboard input and movement, the keys are the ones of the settings
 fn player_move(
     keys: Res<Input<KeyCode>>,
     time: Res<Time>,
     windows: Res<Windows>,
     settings: Res<MovementSettings>,
+    controls: Res<Controls>,
     mut query: Query<(&FlyCam, &mut Transform)>,
 ) {
     let window = windows.get_primary().unwrap();
+    let pressed = |action| window.cursor_locked() && keys.pressed(controls.key(action));
     for (_camera, mut transform) in query.iter_mut() {
         let mut velocity = Vec3::ZERO;
         let local_z = transform.local_z();
         let forward = -Vec3::new(local_z.x, 0., local_z.z);
         let right = Vec3::new(local_z.z, 0., -local_z.x);
 
-        for key in keys.get_pressed() {
-            if window.cursor_locked() {
-                match key {
-                    KeyCode::W => velocity += forward,
-                    KeyCode::S => velocity -= forward,
-                    KeyCode::A => velocity -= right,
-                    KeyCode::D => velocity += right,
-                    KeyCode::Space => velocity += unit_y(),
-                    KeyCode::LShift => velocity -= unit_y(),
-                    _ => (),
-                }
-            }
+        if pressed(Action::Forward) {
+            velocity += forward;
+        }
+        if pressed(Action::Back) {
+            velocity -= forward;
+        }
+        if pressed(Action::Left) {
+            velocity -= right;
+        }
+        if pressed(Action::Right) {
+            velocity += right;
+        }
+        if pressed(Action::Up) {
+            velocity += unit_y();
+        }
+        if pressed(Action::Down) {
+            velocity -= unit_y();
         }
 
         velocity = velocity.normalize();
@@ -118,14 +129,8 @@ fn player_look(
     }
 }
 
-fn cursor_grab(keys: Res<Input<KeyCode>>, mut windows: ResMut<Windows>) {
-    let window = windows.get_primary_mut().unwrap();
-    if keys.just_pressed(KeyCode::Escape) {
-        toggle_grab_cursor(window);
-    }
-}
-
-/// Same as `PlayerPlugin` but does not spawn a camera
+/// Same as `PlayerPlugin` but does not spawn a camera, Escape opens the menu instead of
+/// ungrabbing the cursor, see `menu::menu_input`
 pub struct NoCameraPlayerPlugin;
 impl Plugin for NoCameraPlayerPlugin {
     fn build(&self, app: &mut AppBuilder) {
@@ -133,7 +138,6 @@ impl Plugin for NoCameraPlayerPlugin {
             .init_resource::<MovementSettings>()
             .add_startup_system(initial_grab_cursor.system())
             .add_system(player_move.system())
-            .add_system(player_look.system())
-            .add_system(cursor_grab.system());
+            .add_system(player_look.system());
     }
 }
\ No newline at end of file
@@ -0,0 +1,414 @@
+//! the binary protocol between the server and the clients, every message is sent as its length
+//! in a little endian u32 followed by a tag byte and the fields of the message, see `Writer`
+
+use std::io::{self, Read, Write};
+
+use crate::block::Block;
+use crate::world::Chunk;
+
+/// has to be increased with every change to the messages, clients of another version are rejected
+pub const VERSION: u16 = 2;
+
+// larger messages are treated as a broken connection instead of being allocated
+pub const MAX_MESSAGE: usize = 1 << 20;
+// longer player names are cut off
+pub const MAX_NAME: usize = 32;
+
+/// messages sent by the clients
+#[derive(Clone, Debug, PartialEq)]
+pub enum ClientMessage {
+    // first message of every connection
+    Hello { version: u16, name: String },
+    // position and rotation of the camera, the rotation is a quaternion
+    Move { position: [f32; 3], rotation: [f32; 4] },
+    // places or removes a block like `controll::build`, `AIR` removes
+    SetBlock { position: [i32; 3], block: Block },
+}
+
+/// messages sent by the server
+#[derive(Clone, Debug, PartialEq)]
+pub enum ServerMessage {
+    // answer to an accepted `Hello`
+    Welcome { player: u32, seed: u32, sea_level: i32, spawn: [f32; 3] },
+    // answer to a `Hello` that can not be accepted, the connection is closed afterwards
+    Rejected { reason: String },
+    Chunk { position: [i32; 3], blocks: Vec<(u16, Block)> },
+    BlockChanged { position: [i32; 3], block: Block },
+    PlayerJoined { player: u32, name: String },
+    PlayerMoved { player: u32, position: [f32; 3], rotation: [f32; 4] },
+    PlayerLeft { player: u32 },
+    // the chunk left the view of the player, it is sent again when it comes back
+    ChunkUnloaded { position: [i32; 3] },
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+/// the fields of a message in little endian
+#[derive(Default)]
+pub struct Writer {
+    pub data: Vec<u8>,
+}
+impl Writer {
+    pub fn u8(&mut self, value: u8) {
+        self.data.push(value);
+    }
+    pub fn u16(&mut self, value: u16) {
+        self.data.extend_from_slice(&value.to_le_bytes());
+    }
+    pub fn u32(&mut self, value: u32) {
+        self.data.extend_from_slice(&value.to_le_bytes());
+    }
+    pub fn i32(&mut self, value: i32) {
+        self.data.extend_from_slice(&value.to_le_bytes());
+    }
+    pub fn f32(&mut self, value: f32) {
+        self.data.extend_from_slice(&value.to_le_bytes());
+    }
+    pub fn string(&mut self, value: &str) {
+        // longer strings are cut off in front of the character that does not fit anymore
+        let mut length = value.len().min(u16::MAX as usize);
+        while !value.is_char_boundary(length) {
+            length -= 1;
+        }
+        self.u16(length as u16);
+        self.data.extend_from_slice(&value.as_bytes()[..length]);
+    }
+    pub fn block(&mut self, block: Block) {
+        self.u16(block.id);
+        self.u8(block.state);
+    }
+    pub fn position(&mut self, position: [i32; 3]) {
+        position.iter().for_each(|axis| self.i32(*axis));
+    }
+    pub fn floats(&mut self, values: &[f32]) {
+        values.iter().for_each(|value| self.f32(*value));
+    }
+}
+
+/// reads the fields written by `Writer`, running out of data is an error
+pub struct Reader<'a> {
+    pub data: &'a [u8],
+}
+impl<'a> Reader<'a> {
+    fn take(&mut self, length: usize) -> io::Result<&'a [u8]> {
+        if self.data.len() < length {
+            return Err(invalid("message ends early"));
+        }
+        let (taken, rest) = self.data.split_at(length);
+        self.data = rest;
+        Ok(taken)
+    }
+    pub fn u8(&mut self) -> io::Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+    pub fn u16(&mut self) -> io::Result<u16> {
+        let bytes = self.take(2)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+    pub fn u32(&mut self) -> io::Result<u32> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+    pub fn i32(&mut self) -> io::Result<i32> {
+        Ok(self.u32()? as i32)
+    }
+    pub fn f32(&mut self) -> io::Result<f32> {
+        Ok(f32::from_bits(self.u32()?))
+    }
+    pub fn string(&mut self) -> io::Result<String> {
+        let length = self.u16()? as usize;
+        String::from_utf8(self.take(length)?.to_vec()).map_err(|_| invalid("string is not utf-8"))
+    }
+    pub fn block(&mut self) -> io::Result<Block> {
+        Ok(Block {id: self.u16()?, state: self.u8()?})
+    }
+    pub fn position(&mut self) -> io::Result<[i32; 3]> {
+        Ok([self.i32()?, self.i32()?, self.i32()?])
+    }
+    pub fn floats<const N: usize>(&mut self) -> io::Result<[f32; N]> {
+        let mut values = [0.0; N];
+        for value in values.iter_mut() {
+            *value = self.f32()?;
+        }
+        Ok(values)
+    }
+}
+
+/// the blocks of a chunk as runs of the same block in the order of x, y and z
+pub fn encode_chunk(chunk: &Chunk) -> Vec<(u16, Block)> {
+    let mut runs: Vec<(u16, Block)> = Vec::new();
+    for x in 0..32 {
+        for y in 0..32 {
+            for z in 0..32 {
+                let block = chunk.get([x, y, z]);
+                match runs.last_mut() {
+                    Some((length, last)) if *last == block && *length < u16::MAX => *length += 1,
+                    _ => runs.push((1, block)),
+                }
+            }
+        }
+    }
+    runs
+}
+
+/// fills a chunk with the runs of `encode_chunk`, fails if they do not cover the chunk exactly
+pub fn decode_chunk(position: [i32; 3], runs: &[(u16, Block)]) -> io::Result<Chunk> {
+    let total: usize = runs.iter().map(|(length, _)| *length as usize).sum();
+    if total != 32 * 32 * 32 {
+        return Err(invalid("chunk does not have 32768 blocks"));
+    }
+
+    let mut chunk = Chunk::new(position[0], position[1], position[2]);
+    let mut index = 0;
+    for (length, block) in runs.iter() {
+        for _ in 0..*length {
+            chunk.set([index / 1024, index / 32 % 32, index % 32], *block);
+            index += 1;
+        }
+    }
+    Ok(chunk)
+}
+
+impl ClientMessage {
+    pub fn encode(&self, writer: &mut Writer) {
+        match self {
+            ClientMessage::Hello { version, name } => {
+                writer.u8(0);
+                writer.u16(*version);
+                writer.string(name);
+            }
+            ClientMessage::Move { position, rotation } => {
+                writer.u8(1);
+                writer.floats(position);
+                writer.floats(rotation);
+            }
+            ClientMessage::SetBlock { position, block } => {
+                writer.u8(2);
+                writer.position(*position);
+                writer.block(*block);
+            }
+        }
+    }
+
+    pub fn decode(reader: &mut Reader) -> io::Result<Self> {
+        Ok(match reader.u8()? {
+            0 => ClientMessage::Hello {version: reader.u16()?, name: reader.string()?},
+            1 => ClientMessage::Move {position: reader.floats()?, rotation: reader.floats()?},
+            2 => ClientMessage::SetBlock {position: reader.position()?, block: reader.block()?},
+            _ => return Err(invalid("unknown client message")),
+        })
+    }
+}
+
+impl ServerMessage {
+    pub fn encode(&self, writer: &mut Writer) {
+        match self {
+            ServerMessage::Welcome { player, seed, sea_level, spawn } => {
+                writer.u8(0);
+                writer.u32(*player);
+                writer.u32(*seed);
+                writer.i32(*sea_level);
+                writer.floats(spawn);
+            }
+            ServerMessage::Rejected { reason } => {
+                writer.u8(1);
+                writer.string(reason);
+            }
+            ServerMessage::Chunk { position, blocks } => {
+                writer.u8(2);
+                writer.position(*position);
+                writer.u32(blocks.len() as u32);
+                for (length, block) in blocks.iter() {
+                    writer.u16(*length);
+                    writer.block(*block);
+                }
+            }
+            ServerMessage::BlockChanged { position, block } => {
+                writer.u8(3);
+                writer.position(*position);
+                writer.block(*block);
+            }
+            ServerMessage::PlayerJoined { player, name } => {
+                writer.u8(4);
+                writer.u32(*player);
+                writer.string(name);
+            }
+            ServerMessage::PlayerMoved { player, position, rotation } => {
+                writer.u8(5);
+                writer.u32(*player);
+                writer.floats(position);
+                writer.floats(rotation);
+            }
+            ServerMessage::PlayerLeft { player } => {
+                writer.u8(6);
+                writer.u32(*player);
+            }
+            ServerMessage::ChunkUnloaded { position } => {
+                writer.u8(7);
+                writer.position(*position);
+            }
+        }
+    }
+
+    pub fn decode(reader: &mut Reader) -> io::Result<Self> {
+        Ok(match reader.u8()? {
+            0 => ServerMessage::Welcome {
+                player: reader.u32()?,
+                seed: reader.u32()?,
+                sea_level: reader.i32()?,
+                spawn: reader.floats()?,
+            },
+            1 => ServerMessage::Rejected {reason: reader.string()?},
+            2 => {
+                let position = reader.position()?;
+                // every run takes 5 bytes, so the count can not exceed what is left of the message
+                let runs = reader.u32()? as usize;
+                if runs * 5 > reader.data.len() {
+                    return Err(invalid("message ends early"));
+                }
+                let mut blocks = Vec::with_capacity(runs);
+                for _ in 0..runs {
+                    blocks.push((reader.u16()?, reader.block()?));
+                }
+                ServerMessage::Chunk {position, blocks}
+            }
+            3 => ServerMessage::BlockChanged {position: reader.position()?, block: reader.block()?},
+            4 => ServerMessage::PlayerJoined {player: reader.u32()?, name: reader.string()?},
+            5 => ServerMessage::PlayerMoved {player: reader.u32()?, position: reader.floats()?, rotation: reader.floats()?},
+            6 => ServerMessage::PlayerLeft {player: reader.u32()?},
+            7 => ServerMessage::ChunkUnloaded {position: reader.position()?},
+            _ => return Err(invalid("unknown server message")),
+        })
+    }
+}
+
+// writes the length of the message in front of it
+fn write_frame(stream: &mut impl Write, writer: Writer) -> io::Result<()> {
+    stream.write_all(&(writer.data.len() as u32).to_le_bytes())?;
+    stream.write_all(&writer.data)
+}
+
+fn read_frame(stream: &mut impl Read) -> io::Result<Vec<u8>> {
+    let mut length = [0u8; 4];
+    stream.read_exact(&mut length)?;
+    let length = u32::from_le_bytes(length) as usize;
+    if length > MAX_MESSAGE {
+        return Err(invalid("message is too large"));
+    }
+    let mut data = vec![0u8; length];
+    stream.read_exact(&mut data)?;
+    Ok(data)
+}
+
+pub fn write_client_message(stream: &mut impl Write, message: &ClientMessage) -> io::Result<()> {
+    let mut writer = Writer::default();
+    message.encode(&mut writer);
+    write_frame(stream, writer)
+}
+
+pub fn write_server_message(stream: &mut impl Write, message: &ServerMessage) -> io::Result<()> {
+    let mut writer = Writer::default();
+    message.encode(&mut writer);
+    write_frame(stream, writer)
+}
+
+/// blocks until a whole message arrived, bytes after the message that belong to it are an error
+pub fn read_client_message(stream: &mut impl Read) -> io::Result<ClientMessage> {
+    let data = read_frame(stream)?;
+    let mut reader = Reader {data: &data};
+    let message = ClientMessage::decode(&mut reader)?;
+    if !reader.data.is_empty() {
+        return Err(invalid("message is longer than expected"));
+    }
+    Ok(message)
+}
+
+pub fn read_server_message(stream: &mut impl Read) -> io::Result<ServerMessage> {
+    let data = read_frame(stream)?;
+    let mut reader = Reader {data: &data};
+    let message = ServerMessage::decode(&mut reader)?;
+    if !reader.data.is_empty() {
+        return Err(invalid("message is longer than expected"));
+    }
+    Ok(message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block;
+
+    #[test]
+    fn messages_round_trip() {
+        let messages = [
+            ServerMessage::Welcome {player: 3, seed: 1457087, sea_level: -4, spawn: [0.5, 65.0, -2.0]},
+            ServerMessage::Rejected {reason: "wrong version".to_string()},
+            ServerMessage::BlockChanged {position: [-1, 40, 33], block: Block {id: block::WATER, state: 3}},
+            ServerMessage::PlayerJoined {player: 7, name: "ünïcode".to_string()},
+            ServerMessage::PlayerMoved {player: 7, position: [1.0, 2.0, 3.0], rotation: [0.0, 0.6, 0.0, 0.8]},
+            ServerMessage::PlayerLeft {player: 7},
+            ServerMessage::ChunkUnloaded {position: [2, -3, 0]},
+        ];
+        let mut stream: Vec<u8> = Vec::new();
+        for message in messages.iter() {
+            write_server_message(&mut stream, message).unwrap();
+        }
+        let mut reader = &stream[..];
+        for message in messages.iter() {
+            assert_eq!(&read_server_message(&mut reader).unwrap(), message);
+        }
+
+        let hello = ClientMessage::Hello {version: VERSION, name: "alice".to_string()};
+        let mut stream: Vec<u8> = Vec::new();
+        write_client_message(&mut stream, &hello).unwrap();
+        assert_eq!(read_client_message(&mut &stream[..]).unwrap(), hello);
+    }
+
+    #[test]
+    fn long_strings_are_cut_between_characters() {
+        // the last ü would be split by the limit of u16::MAX bytes
+        let reason = format!("{}ü", "a".repeat(u16::MAX as usize - 1));
+        let mut stream: Vec<u8> = Vec::new();
+        write_server_message(&mut stream, &ServerMessage::Rejected {reason}).unwrap();
+        let expected = ServerMessage::Rejected {reason: "a".repeat(u16::MAX as usize - 1)};
+        assert_eq!(read_server_message(&mut &stream[..]).unwrap(), expected);
+    }
+
+    #[test]
+    fn chunks_round_trip() {
+        let mut chunk = Chunk::new(-1, 2, 0);
+        chunk.set([0, 0, 0], Block::new(block::STONE));
+        chunk.set([31, 31, 31], Block {id: block::LAVA, state: 2});
+        for y in 0..10 {
+            chunk.set([5, y, 7], Block::new(block::DIRT));
+        }
+
+        let runs = encode_chunk(&chunk);
+        let decoded = decode_chunk([-1, 2, 0], &runs).unwrap();
+        for x in 0..32 {
+            for y in 0..32 {
+                for z in 0..32 {
+                    assert_eq!(decoded.get([x, y, z]), chunk.get([x, y, z]));
+                }
+            }
+        }
+        assert!(decode_chunk([0, 0, 0], &runs[1..]).is_err());
+    }
+
+    #[test]
+    fn broken_messages_are_errors() {
+        let mut stream: Vec<u8> = Vec::new();
+        write_server_message(&mut stream, &ServerMessage::PlayerLeft {player: 1}).unwrap();
+
+        // cut off in the middle
+        assert!(read_server_message(&mut &stream[..stream.len() - 1]).is_err());
+        // unknown tag
+        let unknown = [1, 0, 0, 0, 200];
+        assert!(read_server_message(&mut &unknown[..]).is_err());
+        // longer than allowed, without sending the data
+        let huge = ((MAX_MESSAGE + 1) as u32).to_le_bytes();
+        assert!(read_server_message(&mut &huge[..]).is_err());
+    }
+}
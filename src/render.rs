@@ -0,0 +1,214 @@
+use bevy::prelude::*;
+use bevy::reflect::TypeUuid;
+use bevy::render::mesh::{Indices, VertexAttributeValues};
+use bevy::render::pipeline::{PipelineDescriptor, PrimitiveTopology, RenderPipeline};
+use bevy::render::render_graph::{base, AssetRenderResourcesNode, RenderGraph};
+use bevy::render::renderer::RenderResources;
+use bevy::render::shader::ShaderStages;
+use bevy::render::texture::{Extent3d, ImageType, TextureDimension, TextureFormat};
+
+use voxel::atlas::{self, Source, Tiles};
+use voxel::custom_model;
+use voxel::mesh::MeshBuffers;
+
+use crate::Materials;
+
+/// the block atlas on the gpu with the tiles of its textures
+#[derive(Default)]
+pub struct BlockAtlas {
+    pub texture: Handle<Texture>,
+    pub tiles: Tiles,
+}
+
+// reads a png of the texture directory, textures that are not 16 pixels wide or not made of whole frames are skipped
+fn read_source(path: &std::path::Path) -> Result<Source, String> {
+    let name = path.file_stem().and_then(|name| name.to_str()).ok_or("invalid file name")?.to_string();
+    let bytes = std::fs::read(path).map_err(|e| e.to_string())?;
+    let mut texture = Texture::from_buffer(&bytes, ImageType::Extension("png")).map_err(|e| e.to_string())?;
+    if texture.format != TextureFormat::Rgba8UnormSrgb {
+        texture = texture.convert(TextureFormat::Rgba8UnormSrgb).ok_or("unsupported pixel format")?;
+    }
+
+    let (width, height) = (texture.size.width, texture.size.height);
+    if width != atlas::TILE || height == 0 || !height.is_multiple_of(atlas::TILE) {
+        return Err(format!(
+            "is {}x{} pixels instead of {} pixels wide with frames of {} pixels",
+            width, height, atlas::TILE, atlas::TILE,
+        ));
+    }
+    Ok(Source {name, width, height, data: texture.data})
+}
+
+/// packs the textures of `assets/textures/blocks` into the block atlas
+pub fn build_atlas(mut commands: Commands, mut textures: ResMut<Assets<Texture>>) {
+    let directory = bevy::asset::FileAssetIo::get_root_path().join("assets").join(atlas::DIRECTORY);
+
+    let mut paths: Vec<std::path::PathBuf> = match std::fs::read_dir(&directory) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().map(|extension| extension == "png").unwrap_or(false))
+            .collect(),
+        Err(e) => {
+            warn!("could not read the block textures in {}: {}", atlas::DIRECTORY, e);
+            Vec::new()
+        }
+    };
+    // keeps the layout the same between runs
+    paths.sort();
+
+    let mut sources = Vec::new();
+    for path in paths.iter() {
+        match read_source(path) {
+            Ok(source) => sources.push(source),
+            Err(e) => warn!("skipping block texture {}: {}", path.display(), e),
+        }
+    }
+
+    let (packed, tiles) = atlas::pack(&sources);
+    let texture = Texture::new(
+        Extent3d::new(packed.width, packed.height, 1),
+        TextureDimension::D2,
+        packed.data,
+        TextureFormat::Rgba8UnormSrgb,
+    );
+    commands.insert_resource(BlockAtlas {texture: textures.add(texture), tiles});
+}
+
+/// reads the models of the custom blocks, see `custom_model::load_models`
+pub fn load_models(mut commands: Commands, atlas: Res<BlockAtlas>) {
+    let assets = bevy::asset::FileAssetIo::get_root_path().join("assets");
+    let (models, warnings) = custom_model::load_models(&assets, &atlas.tiles);
+    for warning in warnings {
+        warn!("{}", warning);
+    }
+    commands.insert_resource(models);
+}
+
+/// material of chunk meshes, the light levels are baked into the vertices
+#[derive(RenderResources, Default, TypeUuid)]
+#[uuid = "3c54da14-70ca-4ad1-889a-948a3bb050d2"]
+pub struct ChunkMaterial {
+    // tint of the texture, the alpha is used by the translucent render path
+    pub color: Color,
+    pub texture: Handle<Texture>,
+    // direction towards the sun and its intensity in w, set by `daytime::update_sky`
+    pub sun: Vec4,
+    // light of the sky itself, also reaches faces turned away from the sun
+    pub ambient: Color,
+    // texels with a lower alpha are discarded
+    pub alpha_cutoff: f32,
+    // seconds that drive the animated textures, set by `animate_textures`
+    pub time: f32,
+}
+
+/// creates the chunk pipeline and the block materials
+pub fn setup_chunk_rendering(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    atlas: Res<BlockAtlas>,
+    mut pipelines: ResMut<Assets<PipelineDescriptor>>,
+    mut chunk_materials: ResMut<Assets<ChunkMaterial>>,
+    mut render_graph: ResMut<RenderGraph>,
+) {
+    let shader_stages = ShaderStages {
+        vertex: asset_server.load::<Shader, _>("shaders/chunk.vert"),
+        fragment: Some(asset_server.load::<Shader, _>("shaders/chunk.frag")),
+    };
+    let chunk_pipeline = pipelines.add(PipelineDescriptor::default_config(shader_stages.clone()));
+
+    let mut translucent_pipeline = PipelineDescriptor::default_config(shader_stages);
+    if let Some(depth_stencil) = translucent_pipeline.depth_stencil.as_mut() {
+        depth_stencil.depth_write_enabled = false;
+    }
+    let translucent_pipeline = pipelines.add(translucent_pipeline);
+
+    // binds the ChunkMaterial to the shader
+    render_graph.add_system_node(
+        "chunk_material",
+        AssetRenderResourcesNode::<ChunkMaterial>::new(true),
+    );
+    render_graph
+        .add_node_edge("chunk_material", base::node::MAIN_PASS)
+        .unwrap();
+
+    let block_texture_handle = atlas.texture.clone();
+
+    let blocks = chunk_materials.add(ChunkMaterial {
+        color: Color::rgba(1.0, 1.0, 1.0, 1.0),
+        texture: block_texture_handle.clone(),
+        alpha_cutoff: 0.0,
+        ..Default::default()
+    });
+
+    // leaves and plants are either fully opaque or fully transparent per texel
+    let cutout_blocks = chunk_materials.add(ChunkMaterial {
+        color: Color::rgba(1.0, 1.0, 1.0, 1.0),
+        texture: block_texture_handle.clone(),
+        alpha_cutoff: 0.5,
+        ..Default::default()
+    });
+
+    let translucent_blocks = chunk_materials.add(ChunkMaterial {
+        color: Color::rgba(1.0, 1.0, 1.0, 0.6),
+        texture: block_texture_handle,
+        alpha_cutoff: 0.01,
+        ..Default::default()
+    });
+
+    commands.insert_resource(Materials {
+        blocks,
+        cutout_blocks,
+        translucent_blocks,
+        chunk_pipeline,
+        translucent_pipeline,
+    });
+}
+
+/// advances the animated textures of all chunk materials, the meshes stay the same
+pub fn animate_textures(
+    time: Res<Time>,
+    materials: Res<Materials>,
+    mut chunk_materials: ResMut<Assets<ChunkMaterial>>,
+) {
+    // wraps around before the precision of f32 gets too low for the frames
+    let seconds = (time.seconds_since_startup() % 3600.0) as f32;
+
+    for handle in [&materials.blocks, &materials.cutout_blocks, &materials.translucent_blocks].iter() {
+        if let Some(material) = chunk_materials.get_mut(*handle) {
+            material.time = seconds;
+        }
+    }
+}
+
+// name of the packed vertex attribute of chunk meshes, see `chunk.vert`
+pub const ATTRIBUTE_DATA: &str = "Vertex_Data";
+
+/// mesh of packed vertices that can be drawn with the chunk pipeline
+pub fn packed_mesh(buffers: MeshBuffers) -> Mesh {
+    let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+    mesh.set_attribute(ATTRIBUTE_DATA, VertexAttributeValues::Uint3(buffers.vertices));
+    mesh.set_indices(Some(Indices::U32(buffers.indices)));
+    mesh
+}
+
+/// components to draw a mesh with the chunk pipeline, a `ChunkMaterial` has to be inserted as well
+pub fn chunk_bundle(
+    mesh: Handle<Mesh>,
+    materials: &Materials,
+    transform: Transform,
+    translucent: bool,
+) -> MeshBundle {
+    MeshBundle {
+        mesh,
+        render_pipelines: RenderPipelines::from_pipelines(vec![RenderPipeline::new(
+            if translucent { materials.translucent_pipeline.clone() } else { materials.chunk_pipeline.clone() },
+        )]),
+        // transparent entities get sorted back to front
+        visible: Visible {
+            is_visible: true,
+            is_transparent: translucent,
+        },
+        transform,
+        ..Default::default()
+    }
+}
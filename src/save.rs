@@ -0,0 +1,132 @@
+//! worlds on disk, a directory with the options of the world in `world.json` and one file per chunk
+//! that was changed since it was generated, untouched chunks are generated again from the seed
+
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::protocol::{self, Reader, Writer};
+use crate::world::Chunk;
+
+// has to be increased with every change to the chunk files
+const VERSION: u16 = 1;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct WorldMeta {
+    pub seed: u32,
+    pub sea_level: i32,
+}
+
+pub struct WorldSave {
+    pub directory: PathBuf,
+}
+
+impl WorldSave {
+    /// creates the directory if it does not exist yet
+    pub fn open(directory: impl Into<PathBuf>) -> io::Result<Self> {
+        let directory = directory.into();
+        fs::create_dir_all(directory.join("chunks"))?;
+        Ok(WorldSave {directory})
+    }
+
+    /// `None` for new worlds
+    pub fn load_meta(&self) -> io::Result<Option<WorldMeta>> {
+        let file = match fs::read_to_string(self.directory.join("world.json")) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        serde_json::from_str(&file).map(Some).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    pub fn save_meta(&self, meta: &WorldMeta) -> io::Result<()> {
+        let file = serde_json::to_string_pretty(meta).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        fs::write(self.directory.join("world.json"), file)
+    }
+
+    fn chunk_path(&self, position: [i32; 3]) -> PathBuf {
+        self.directory.join("chunks").join(format!("{}_{}_{}.bin", position[0], position[1], position[2]))
+    }
+
+    /// `None` if the chunk was never saved
+    pub fn load_chunk(&self, position: [i32; 3]) -> io::Result<Option<Chunk>> {
+        let data = match fs::read(self.chunk_path(position)) {
+            Ok(data) => data,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+
+        let mut reader = Reader {data: &data};
+        if reader.u16()? != VERSION {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "chunk of another version"));
+        }
+        let mut runs = Vec::new();
+        while !reader.data.is_empty() {
+            runs.push((reader.u16()?, reader.block()?));
+        }
+        protocol::decode_chunk(position, &runs).map(Some)
+    }
+
+    /// the blocks of the chunk in the runs of `protocol::encode_chunk`, the light is calculated again when loading
+    pub fn save_chunk(&self, chunk: &Chunk) -> io::Result<()> {
+        let mut writer = Writer::default();
+        writer.u16(VERSION);
+        for (length, block) in protocol::encode_chunk(chunk) {
+            writer.u16(length);
+            writer.block(block);
+        }
+
+        // a crash while writing leaves the old chunk behind instead of a broken one
+        let path = self.chunk_path([chunk.x, chunk.y, chunk.z]);
+        let temporary = path.with_extension("tmp");
+        fs::write(&temporary, &writer.data)?;
+        fs::rename(&temporary, &path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::{self, Block};
+
+    // a fresh directory for every test, the tests run in parallel
+    fn save(name: &str) -> WorldSave {
+        let directory = std::env::temp_dir().join(format!("voxel-save-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        WorldSave::open(directory).unwrap()
+    }
+
+    #[test]
+    fn meta_round_trips() {
+        let save = save("meta");
+        assert_eq!(save.load_meta().unwrap(), None);
+
+        let meta = WorldMeta {seed: 42, sea_level: -3};
+        save.save_meta(&meta).unwrap();
+        assert_eq!(save.load_meta().unwrap(), Some(meta));
+    }
+
+    #[test]
+    fn chunks_round_trip() {
+        let save = save("chunks");
+        assert!(save.load_chunk([1, -2, 3]).unwrap().is_none());
+
+        let mut chunk = Chunk::new(1, -2, 3);
+        chunk.set([0, 0, 0], Block::new(block::STONE));
+        chunk.set([31, 31, 31], Block {id: block::WATER, state: 5});
+        save.save_chunk(&chunk).unwrap();
+
+        let loaded = save.load_chunk([1, -2, 3]).unwrap().unwrap();
+        assert_eq!([loaded.x, loaded.y, loaded.z], [1, -2, 3]);
+        assert_eq!(protocol::encode_chunk(&loaded), protocol::encode_chunk(&chunk));
+    }
+
+    #[test]
+    fn other_versions_are_errors() {
+        let save = save("version");
+        fs::write(save.chunk_path([0, 0, 0]), (VERSION + 1).to_le_bytes()).unwrap();
+        assert!(save.load_chunk([0, 0, 0]).is_err());
+    }
+}
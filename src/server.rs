@@ -0,0 +1,602 @@
+//! the authoritative world of a multiplayer game, clients connect over tcp and talk the `protocol`
+
+use std::collections::{HashMap, HashSet};
+use std::io::{self, BufWriter, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::block::{self, Block, BlockId};
+use crate::coords::BlockPos;
+use crate::fluid::{self, FluidSimulation};
+use crate::protocol::{self, ClientMessage, ServerMessage, MAX_NAME, VERSION};
+use crate::save::{WorldMeta, WorldSave};
+use crate::world::{self, Chunk, World};
+
+// seconds between two updates of the world, the same as the fluid simulation of the game
+pub const TICK: f64 = fluid::TICK;
+// the chunks sent to a client during one tick, the nearest ones first
+const CHUNKS_PER_TICK: usize = 16;
+// players can only edit blocks this close to their camera
+pub const REACH: f32 = 8.0;
+// players can not leave this far from the origin on any axis, which keeps chunk positions far from overflowing
+pub const WORLD_BORDER: f32 = 1_000_000.0;
+// blocks per second a player can move, longer moves are cut short and catch up during the next ticks
+const MAX_SPEED: f32 = 100.0;
+// how often changed chunks are written to the world directory
+const SAVE_INTERVAL: Duration = Duration::from_secs(5);
+
+pub struct ServerSettings {
+    pub seed: u32,
+    pub sea_level: i32,
+    // chunks around a player that are generated and sent to it, measured horizontally
+    pub view_distance: i32,
+    // the heights of the chunks that are generated, like `chunk::generate_spawn`
+    pub heights: std::ops::Range<i32>,
+    // the number of blocks of `assets/models/blocks.json`, custom blocks above are rejected
+    pub custom_blocks: usize,
+    // keeps the world between runs, the seed and sea level of an existing world win over the ones above
+    pub world: Option<PathBuf>,
+}
+impl Default for ServerSettings {
+    fn default() -> Self {
+        ServerSettings {seed: 1457087, sea_level: 44, view_distance: 2, heights: -3..5, custom_blocks: 0, world: None}
+    }
+}
+
+// what the connection threads tell the server
+enum Event {
+    Connected(u32, TcpStream),
+    Message(u32, ClientMessage),
+    Disconnected(u32),
+}
+
+struct Client {
+    name: Option<String>,
+    stream: BufWriter<TcpStream>,
+    position: [f32; 3],
+    rotation: [f32; 4],
+    moved: bool,
+    // blocks the player can still move until the next tick
+    allowance: f32,
+    // the chunks the client has, the ones that leave its view are unloaded again
+    sent_chunks: HashSet<[i32; 3]>,
+    // the column the client was in when the last chunk of its view was sent
+    complete: Option<[i32; 2]>,
+}
+
+pub struct Server {
+    pub settings: ServerSettings,
+    pub world: World,
+    fluids: FluidSimulation,
+    clients: HashMap<u32, Client>,
+    listener: TcpListener,
+    events: Receiver<Event>,
+    sender: Sender<Event>,
+    // where new players appear, above the terrain at the origin
+    spawn: [f32; 3],
+    save: Option<WorldSave>,
+    // chunks with changes that are not written to the save yet
+    unsaved: HashSet<[i32; 3]>,
+    last_save: Instant,
+}
+
+impl Server {
+    /// binds the listener, the world is generated when `run` is called
+    pub fn bind(address: impl ToSocketAddrs, mut settings: ServerSettings) -> io::Result<Self> {
+        let listener = TcpListener::bind(address)?;
+
+        let save = match &settings.world {
+            Some(directory) => {
+                let save = WorldSave::open(directory)?;
+                match save.load_meta()? {
+                    Some(meta) => {
+                        settings.seed = meta.seed;
+                        settings.sea_level = meta.sea_level;
+                    }
+                    None => save.save_meta(&WorldMeta {seed: settings.seed, sea_level: settings.sea_level})?,
+                }
+                Some(save)
+            }
+            None => None,
+        };
+
+        let (sender, events) = mpsc::channel();
+        Ok(Server {
+            world: World::new(settings.seed, settings.sea_level),
+            settings,
+            fluids: FluidSimulation::default(),
+            clients: HashMap::new(),
+            listener,
+            events,
+            sender,
+            spawn: [0.0, 65.0, 0.0],
+            save,
+            unsaved: HashSet::new(),
+            last_save: Instant::now(),
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// accepts clients and updates the world until the process ends
+    pub fn run(mut self) -> io::Result<()> {
+        self.generate_around([0.0, 0.0]);
+
+        let listener = self.listener.try_clone()?;
+        let sender = self.sender.clone();
+        thread::spawn(move || accept(listener, sender));
+
+        let tick = Duration::from_secs_f64(TICK);
+        let mut next_tick = Instant::now() + tick;
+        loop {
+            // a steady stream of messages must not hold back the ticks,
+            // a server that fell behind still handles one event between two ticks
+            if Instant::now() >= next_tick {
+                self.tick();
+                next_tick += tick;
+            }
+            match self.events.recv_timeout(next_tick.saturating_duration_since(Instant::now())) {
+                Ok(event) => self.handle(event),
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => return Ok(()),
+            }
+        }
+    }
+
+    fn handle(&mut self, event: Event) {
+        match event {
+            Event::Connected(player, stream) => {
+                println!("player {} connected from {:?}", player, stream.peer_addr());
+                self.clients.insert(player, Client {
+                    name: None,
+                    stream: BufWriter::new(stream),
+                    position: self.spawn,
+                    rotation: [0.0, 0.0, 0.0, 1.0],
+                    moved: false,
+                    allowance: MAX_SPEED * TICK as f32,
+                    sent_chunks: HashSet::new(),
+                    complete: None,
+                });
+            }
+            Event::Message(player, message) => self.receive(player, message),
+            Event::Disconnected(player) => self.disconnect(player),
+        }
+    }
+
+    fn receive(&mut self, player: u32, message: ClientMessage) {
+        let client = match self.clients.get_mut(&player) {
+            Some(client) => client,
+            None => return,
+        };
+
+        match message {
+            ClientMessage::Hello { version, name } => {
+                if client.name.is_some() {
+                    return;
+                }
+                if version != VERSION {
+                    let reason = format!("the server speaks version {} of the protocol, not {}", VERSION, version);
+                    let _ = send(client, &ServerMessage::Rejected {reason});
+                    let _ = client.stream.flush();
+                    self.disconnect(player);
+                    return;
+                }
+
+                let name: String = name.chars().filter(|c| !c.is_control()).take(MAX_NAME).collect();
+                println!("player {} joined as {}", player, name);
+                client.name = Some(name.clone());
+
+                let welcome = ServerMessage::Welcome {
+                    player,
+                    seed: self.settings.seed,
+                    sea_level: self.settings.sea_level,
+                    spawn: self.spawn,
+                };
+                let mut messages = vec![welcome];
+                // the players that are already there
+                for (other, other_client) in self.clients.iter() {
+                    if let (true, Some(other_name)) = (*other != player, &other_client.name) {
+                        messages.push(ServerMessage::PlayerJoined {player: *other, name: other_name.clone()});
+                        messages.push(ServerMessage::PlayerMoved {
+                            player: *other,
+                            position: other_client.position,
+                            rotation: other_client.rotation,
+                        });
+                    }
+                }
+                for message in messages.iter() {
+                    self.send_to(player, message);
+                }
+                self.broadcast(&ServerMessage::PlayerJoined {player, name}, Some(player));
+            }
+            // everything else needs a finished handshake
+            _ if client.name.is_none() => self.disconnect(player),
+            ClientMessage::Move { position, rotation } => {
+                if position.iter().chain(rotation.iter()).all(|value| value.is_finite()) {
+                    client.position = limit_move(client.position, position, &mut client.allowance);
+                    client.rotation = rotation;
+                    client.moved = true;
+                }
+            }
+            ClientMessage::SetBlock { position, block } => {
+                let reach = distance(client.position, [position[0] as f32 + 0.5, position[1] as f32 + 0.5, position[2] as f32 + 0.5]);
+                if let Err(e) = self.validate_edit(position, block, reach) {
+                    println!("rejected the edit of player {} at {:?}: {}", player, position, e);
+                    // the client predicted the edit, so it gets the actual block back
+                    if let Some(actual) = self.world.get(position) {
+                        self.send_to(player, &ServerMessage::BlockChanged {position, block: actual});
+                    }
+                    return;
+                }
+
+                // fluids placed by players are sources
+                let state = if block::is_fluid(block.id) { fluid::SOURCE } else { block.state };
+                self.world.set(position, Block {id: block.id, state});
+            }
+        }
+    }
+
+    // the same rules as `controll::build` has locally, in reach and inside of the loaded world
+    fn validate_edit(&self, position: [i32; 3], block: Block, reach: f32) -> Result<(), String> {
+        if !is_known_block(block.id, self.settings.custom_blocks) {
+            return Err(format!("unknown block {}", block.id));
+        }
+        if reach > REACH {
+            return Err(format!("{:.1} blocks away", reach));
+        }
+        if self.world.get(position).is_none() {
+            return Err("outside of the loaded world".to_string());
+        }
+        Ok(())
+    }
+
+    fn disconnect(&mut self, player: u32) {
+        if let Some(client) = self.clients.remove(&player) {
+            let _ = client.stream.get_ref().shutdown(std::net::Shutdown::Both);
+            if client.name.is_some() {
+                println!("player {} left", player);
+                self.broadcast(&ServerMessage::PlayerLeft {player}, None);
+            }
+        }
+    }
+
+    // advances the fluids, drops unsupported blocks, sends the changes and the chunks that came into view of the players
+    fn tick(&mut self) {
+        for position in self.world.changes.clone() {
+            self.fluids.activate(position);
+        }
+        self.fluids.step(&mut self.world);
+        self.drop_blocks();
+
+        // one message per change, so that clients can match them with the edits they predicted, see `client::Prediction`
+        let changes: Vec<[i32; 3]> = self.world.changes.drain(..).collect();
+        for position in changes {
+            self.unsaved.insert(BlockPos::from(position).chunk().into());
+            if let Some(block) = self.world.get(position) {
+                self.broadcast(&ServerMessage::BlockChanged {position, block}, None);
+            }
+        }
+
+        for client in self.clients.values_mut() {
+            client.allowance = MAX_SPEED * TICK as f32;
+        }
+
+        let moved: Vec<(u32, [f32; 3], [f32; 4])> = self.clients
+            .iter_mut()
+            .filter(|(_, client)| client.moved)
+            .map(|(player, client)| {
+                client.moved = false;
+                (*player, client.position, client.rotation)
+            })
+            .collect();
+        for (player, position, rotation) in moved {
+            self.broadcast(&ServerMessage::PlayerMoved {player, position, rotation}, Some(player));
+        }
+
+        let players: Vec<u32> = self.clients.iter().filter(|(_, client)| client.name.is_some()).map(|(player, _)| *player).collect();
+        for player in players {
+            self.stream_chunks(player);
+        }
+
+        let mut broken = Vec::new();
+        for (player, client) in self.clients.iter_mut() {
+            if client.stream.flush().is_err() {
+                broken.push(*player);
+            }
+        }
+        for player in broken {
+            self.disconnect(player);
+        }
+
+        if self.last_save.elapsed() >= SAVE_INTERVAL {
+            self.save();
+            self.unload_chunks();
+        }
+    }
+
+    // blocks with gravity at or above a change fall straight onto the next solid block,
+    // the positions they leave are changes as well, so whole columns come down in one tick
+    fn drop_blocks(&mut self) {
+        let mut index = 0;
+        while index < self.world.changes.len() {
+            let changed = self.world.changes[index];
+            index += 1;
+
+            for position in [changed, [changed[0], changed[1] + 1, changed[2]]].iter() {
+                let block = match self.world.get_block(*position) {
+                    Some(block) if block::has_gravity(block) => block,
+                    _ => continue,
+                };
+
+                let mut landing = *position;
+                loop {
+                    let below = [landing[0], landing[1] - 1, landing[2]];
+                    match self.world.get_block(below) {
+                        Some(below_block) if !block::is_solid(below_block) => landing = below,
+                        Some(_) => break,
+                        // the bottom of the generated world holds the block where it is
+                        None => {
+                            landing = *position;
+                            break;
+                        }
+                    }
+                }
+                if landing != *position {
+                    self.world.set_block(*position, block::AIR);
+                    self.world.set_block(landing, block);
+                    self.fluids.activate(*position);
+                    self.fluids.activate(landing);
+                }
+            }
+        }
+    }
+
+    /// writes the changed chunks to the world directory, does nothing without one
+    pub fn save(&mut self) {
+        self.last_save = Instant::now();
+        let save = match &self.save {
+            Some(save) => save,
+            None => return self.unsaved.clear(),
+        };
+        for position in self.unsaved.drain() {
+            if let Some(chunk) = self.world.get_chunk(position) {
+                if let Err(e) = save.save_chunk(chunk) {
+                    println!("could not save the chunk at {:?}: {}", position, e);
+                }
+            }
+        }
+    }
+
+    // generates the chunks of the columns around a position that do not exist yet
+    fn generate_around(&mut self, position: [f32; 2]) {
+        let center = [(position[0] / 32.0).floor() as i32, (position[1] / 32.0).floor() as i32];
+        let distance = self.settings.view_distance;
+        for x in center[0] - distance..=center[0] + distance {
+            for z in center[1] - distance..=center[1] + distance {
+                for y in self.settings.heights.clone() {
+                    self.load_or_generate([x, y, z]);
+                }
+            }
+        }
+    }
+
+    // adds the chunk to the world unless it is there already
+    fn load_or_generate(&mut self, position: [i32; 3]) {
+        if self.world.get_chunk(position).is_some() {
+            return;
+        }
+        let chunk = self.load_chunk(position).unwrap_or_else(|| {
+            let mut chunk = Chunk::new(position[0], position[1], position[2]);
+            world::generate_terrain(&mut chunk, self.settings.seed, self.settings.sea_level);
+            chunk
+        });
+        self.world.add_chunk(chunk);
+    }
+
+    // drops the chunks that no player has after they were saved,
+    // without a world directory they stay loaded so that their edits are not lost
+    fn unload_chunks(&mut self) {
+        if self.save.is_none() {
+            return;
+        }
+        let used: HashSet<[i32; 3]> = self.clients.values().flat_map(|client| client.sent_chunks.iter().copied()).collect();
+        let unused: Vec<[i32; 3]> = self.world.chunk_index
+            .iter()
+            .map(|chunk| [chunk.x, chunk.y, chunk.z])
+            .filter(|chunk| !used.contains(chunk))
+            .collect();
+        for chunk in unused {
+            self.world.remove_chunk(chunk);
+        }
+    }
+
+    // the chunk from the world directory, if it was changed in an earlier run
+    fn load_chunk(&self, position: [i32; 3]) -> Option<Chunk> {
+        match self.save.as_ref()?.load_chunk(position) {
+            Ok(chunk) => chunk,
+            Err(e) => {
+                println!("generating the chunk at {:?} again, it could not be loaded: {}", position, e);
+                None
+            }
+        }
+    }
+
+    // sends the nearest chunks in view that the client does not have yet, ring by ring around its column,
+    // and unloads the ones that left its view
+    fn stream_chunks(&mut self, player: u32) {
+        let client = match self.clients.get(&player) {
+            Some(client) => client,
+            None => return,
+        };
+        let center = [(client.position[0] / 32.0).floor() as i32, (client.position[2] / 32.0).floor() as i32];
+        if client.complete == Some(center) {
+            return;
+        }
+        let distance = self.settings.view_distance;
+
+        // one chunk more than the view distance, so that walking along a border does not send chunks back and forth
+        let client = self.clients.get_mut(&player).unwrap();
+        let left: Vec<[i32; 3]> = client.sent_chunks
+            .iter()
+            .filter(|chunk| (chunk[0] - center[0]).abs() > distance + 1 || (chunk[2] - center[1]).abs() > distance + 1)
+            .copied()
+            .collect();
+        for chunk in left {
+            client.sent_chunks.remove(&chunk);
+            let _ = send(client, &ServerMessage::ChunkUnloaded {position: chunk});
+        }
+
+        let mut sent = 0;
+        for ring in 0..=distance {
+            for column in ring_columns(center, ring) {
+                for y in self.settings.heights.clone() {
+                    let chunk = [column[0], y, column[1]];
+                    if self.clients[&player].sent_chunks.contains(&chunk) {
+                        continue;
+                    }
+                    if sent == CHUNKS_PER_TICK {
+                        return;
+                    }
+                    self.load_or_generate(chunk);
+
+                    let blocks = protocol::encode_chunk(self.world.get_chunk(chunk).unwrap());
+                    let client = self.clients.get_mut(&player).unwrap();
+                    client.sent_chunks.insert(chunk);
+                    sent += 1;
+                    if send(client, &ServerMessage::Chunk {position: chunk, blocks}).is_err() {
+                        return;
+                    }
+                }
+            }
+        }
+        self.clients.get_mut(&player).unwrap().complete = Some(center);
+    }
+
+    fn send_to(&mut self, player: u32, message: &ServerMessage) {
+        if let Some(client) = self.clients.get_mut(&player) {
+            // broken connections are dropped on the next flush
+            let _ = send(client, message);
+        }
+    }
+
+    // sends a message to every player that finished the handshake
+    fn broadcast(&mut self, message: &ServerMessage, except: Option<u32>) {
+        for (player, client) in self.clients.iter_mut() {
+            if client.name.is_some() && Some(*player) != except {
+                let _ = send(client, message);
+            }
+        }
+    }
+}
+
+fn send(client: &mut Client, message: &ServerMessage) -> io::Result<()> {
+    protocol::write_server_message(&mut client.stream, message)
+}
+
+// the columns on the border of the square with a distance of `ring` to the center
+fn ring_columns(center: [i32; 2], ring: i32) -> Vec<[i32; 2]> {
+    if ring == 0 {
+        return vec![center];
+    }
+    let mut columns = Vec::new();
+    for offset in -ring..ring {
+        columns.push([center[0] + offset, center[1] - ring]);
+        columns.push([center[0] + ring, center[1] + offset]);
+        columns.push([center[0] - offset, center[1] + ring]);
+        columns.push([center[0] - ring, center[1] - offset]);
+    }
+    columns
+}
+
+// moves towards a position by at most `allowance` blocks, which shrinks by the distance moved,
+// and stops at the world border
+fn limit_move(from: [f32; 3], to: [f32; 3], allowance: &mut f32) -> [f32; 3] {
+    let to = [
+        to[0].clamp(-WORLD_BORDER, WORLD_BORDER),
+        to[1].clamp(-WORLD_BORDER, WORLD_BORDER),
+        to[2].clamp(-WORLD_BORDER, WORLD_BORDER),
+    ];
+    let length = distance(from, to);
+    if length <= *allowance {
+        *allowance -= length;
+        return to;
+    }
+    let scale = *allowance / length;
+    *allowance = 0.0;
+    [from[0] + (to[0] - from[0]) * scale, from[1] + (to[1] - from[1]) * scale, from[2] + (to[2] - from[2]) * scale]
+}
+
+fn distance(a: [f32; 3], b: [f32; 3]) -> f32 {
+    ((a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2) + (a[2] - b[2]).powi(2)).sqrt()
+}
+
+/// built in blocks and the custom blocks of the registry
+pub fn is_known_block(block: BlockId, custom_blocks: usize) -> bool {
+    block < block::BUILT_IN || (block >= block::FIRST_CUSTOM && ((block - block::FIRST_CUSTOM) as usize) < custom_blocks)
+}
+
+// hands every new connection to the server and reads its messages on a thread of its own
+fn accept(listener: TcpListener, sender: Sender<Event>) {
+    let mut next_player = 1;
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(_) => continue,
+        };
+        let _ = stream.set_nodelay(true);
+        let reader = match stream.try_clone() {
+            Ok(reader) => reader,
+            Err(_) => continue,
+        };
+
+        let player = next_player;
+        next_player += 1;
+        if sender.send(Event::Connected(player, stream)).is_err() {
+            return;
+        }
+
+        let sender = sender.clone();
+        thread::spawn(move || {
+            let mut reader = io::BufReader::new(reader);
+            while let Ok(message) = protocol::read_client_message(&mut reader) {
+                if sender.send(Event::Message(player, message)).is_err() {
+                    return;
+                }
+            }
+            let _ = sender.send(Event::Disconnected(player));
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rings_cover_the_square_once() {
+        let mut columns: Vec<[i32; 2]> = (0..=3).flat_map(|ring| ring_columns([5, -2], ring)).collect();
+        columns.sort_unstable();
+        let mut square: Vec<[i32; 2]> = (2..=8).flat_map(|x| (-5..=1).map(move |z| [x, z])).collect();
+        square.sort_unstable();
+        assert_eq!(columns, square);
+    }
+
+    #[test]
+    fn moves_are_limited() {
+        let mut allowance = 10.0;
+        assert_eq!(limit_move([0.0, 0.0, 0.0], [3.0, 4.0, 0.0], &mut allowance), [3.0, 4.0, 0.0]);
+        assert_eq!(allowance, 5.0);
+        // the rest of the way waits for the next tick
+        assert_eq!(limit_move([3.0, 4.0, 0.0], [3.0, 4.0, 100.0], &mut allowance), [3.0, 4.0, 5.0]);
+        assert_eq!(allowance, 0.0);
+
+        let mut allowance = f32::INFINITY;
+        let far = limit_move([0.0, 0.0, 0.0], [1e10, -1e10, 0.0], &mut allowance);
+        assert_eq!(far, [WORLD_BORDER, -WORLD_BORDER, 0.0]);
+    }
+}
@@ -0,0 +1,200 @@
+//! the settings of the game in `settings.toml`, entries missing from the file keep their defaults
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+// the limits of the menu, the file is held to them as well
+pub const MIN_VIEW_DISTANCE: i32 = 1;
+pub const MAX_VIEW_DISTANCE: i32 = 16;
+pub const MIN_FOV: f32 = 30.0;
+pub const MAX_FOV: f32 = 110.0;
+pub const MIN_SENSITIVITY: f32 = 0.005;
+pub const MIN_FLY_SPEED: f32 = 5.0;
+const MIN_WINDOW: [u32; 2] = [320, 240];
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct Settings {
+    // chunks around the spawn that are generated, measured horizontally
+    pub view_distance: i32,
+    // vertical field of view in degrees
+    pub fov: f32,
+    pub sensitivity: f32,
+    // blocks per second while flying
+    pub fly_speed: f32,
+    pub vsync: bool,
+    // width and height in pixels
+    pub window: [u32; 2],
+    pub keys: KeyBindings,
+}
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            view_distance: 2,
+            fov: 45.0,
+            sensitivity: 0.025,
+            fly_speed: 25.0,
+            vsync: false,
+            window: [1200, 800],
+            keys: KeyBindings::default(),
+        }
+    }
+}
+
+impl Settings {
+    /// the defaults if the file does not exist yet, values outside of the limits are moved inside
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let file = match fs::read_to_string(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Settings::default()),
+            Err(e) => return Err(e),
+        };
+        let mut settings: Settings = toml::from_str(&file).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        settings.limit();
+        Ok(settings)
+    }
+
+    // toml knows nan and inf, those get the defaults
+    fn limit(&mut self) {
+        let defaults = Settings::default();
+        let finite = |value: f32, default: f32| if value.is_finite() { value } else { default };
+        self.view_distance = self.view_distance.clamp(MIN_VIEW_DISTANCE, MAX_VIEW_DISTANCE);
+        self.fov = finite(self.fov, defaults.fov).clamp(MIN_FOV, MAX_FOV);
+        self.sensitivity = finite(self.sensitivity, defaults.sensitivity).max(MIN_SENSITIVITY);
+        self.fly_speed = finite(self.fly_speed, defaults.fly_speed).max(MIN_FLY_SPEED);
+        self.window = [self.window[0].max(MIN_WINDOW[0]), self.window[1].max(MIN_WINDOW[1])];
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let file = toml::to_string_pretty(self).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        fs::write(path, file)
+    }
+}
+
+/// what the keys of the keyboard do while flying
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    Forward,
+    Back,
+    Left,
+    Right,
+    Up,
+    Down,
+}
+impl Action {
+    pub const ALL: [Action; 6] = [Action::Forward, Action::Back, Action::Left, Action::Right, Action::Up, Action::Down];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::Forward => "forward",
+            Action::Back => "back",
+            Action::Left => "left",
+            Action::Right => "right",
+            Action::Up => "up",
+            Action::Down => "down",
+        }
+    }
+}
+
+/// the names of the keys are the ones of bevys `KeyCode`, like `W` or `LShift`
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct KeyBindings {
+    pub forward: String,
+    pub back: String,
+    pub left: String,
+    pub right: String,
+    pub up: String,
+    pub down: String,
+}
+impl Default for KeyBindings {
+    fn default() -> Self {
+        KeyBindings {
+            forward: "W".to_string(),
+            back: "S".to_string(),
+            left: "A".to_string(),
+            right: "D".to_string(),
+            up: "Space".to_string(),
+            down: "LShift".to_string(),
+        }
+    }
+}
+impl KeyBindings {
+    pub fn get(&self, action: Action) -> &str {
+        match action {
+            Action::Forward => &self.forward,
+            Action::Back => &self.back,
+            Action::Left => &self.left,
+            Action::Right => &self.right,
+            Action::Up => &self.up,
+            Action::Down => &self.down,
+        }
+    }
+
+    pub fn get_mut(&mut self, action: Action) -> &mut String {
+        match action {
+            Action::Forward => &mut self.forward,
+            Action::Back => &mut self.back,
+            Action::Left => &mut self.left,
+            Action::Right => &mut self.right,
+            Action::Up => &mut self.up,
+            Action::Down => &mut self.down,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("voxel-settings-{}-{}.toml", name, std::process::id()))
+    }
+
+    #[test]
+    fn missing_files_are_the_defaults() {
+        let path = path("missing");
+        let _ = fs::remove_file(&path);
+        assert_eq!(Settings::load(&path).unwrap(), Settings::default());
+    }
+
+    #[test]
+    fn settings_round_trip() {
+        let path = path("round-trip");
+        let mut settings = Settings {view_distance: 6, fov: 70.0, vsync: true, window: [640, 480], ..Default::default()};
+        *settings.keys.get_mut(Action::Up) = "E".to_string();
+        settings.save(&path).unwrap();
+        assert_eq!(Settings::load(&path).unwrap(), settings);
+    }
+
+    #[test]
+    fn missing_entries_keep_their_defaults() {
+        let path = path("partial");
+        fs::write(&path, "fov = 90.0\n[keys]\nforward = \"Up\"\n").unwrap();
+
+        let settings = Settings::load(&path).unwrap();
+        assert_eq!(settings.fov, 90.0);
+        assert_eq!(settings.keys.get(Action::Forward), "Up");
+        assert_eq!(settings.keys.get(Action::Back), "S");
+        assert_eq!(settings.view_distance, Settings::default().view_distance);
+    }
+
+    #[test]
+    fn values_out_of_range_are_corrected() {
+        let path = path("out-of-range");
+        fs::write(&path, "view_distance = 200\nfov = 0.0\nsensitivity = nan\nfly_speed = -3.0\nwindow = [0, 0]\n").unwrap();
+
+        let settings = Settings::load(&path).unwrap();
+        assert_eq!(settings.view_distance, MAX_VIEW_DISTANCE);
+        assert_eq!(settings.fov, MIN_FOV);
+        assert_eq!(settings.sensitivity, Settings::default().sensitivity);
+        assert_eq!(settings.fly_speed, MIN_FLY_SPEED);
+        assert_eq!(settings.window, MIN_WINDOW);
+
+        fs::write(&path, "view_distance = -4\n").unwrap();
+        assert_eq!(Settings::load(&path).unwrap().view_distance, MIN_VIEW_DISTANCE);
+    }
+}
@@ -0,0 +1,220 @@
+use crate::block::Block;
+
+// number of blocks in a chunk
+const VOLUME: usize = 32 * 32 * 32;
+
+/// the blocks of a chunk, chunks made of a single block only store that block,
+/// all others store every different block once and a bit-packed index into them per position
+pub enum BlockStorage {
+    Uniform(Block),
+    Palette(Palette),
+}
+
+pub struct Palette {
+    blocks: Vec<Block>,
+    // how often every block of the palette is used, unused entries get reused
+    counts: Vec<u16>,
+    // bits per index, always 1, 2, 4, 8 or 16 so that an index never spans two words
+    bits: usize,
+    data: Vec<u64>,
+}
+
+impl Palette {
+    fn new(bits: usize) -> Self {
+        Palette {
+            blocks: Vec::new(),
+            counts: Vec::new(),
+            bits,
+            data: vec![0; VOLUME * bits / 64],
+        }
+    }
+
+    fn get_index(&self, position: usize) -> usize {
+        let per_word = 64 / self.bits;
+        let shift = (position % per_word) * self.bits;
+        ((self.data[position / per_word] >> shift) & ((1 << self.bits) - 1)) as usize
+    }
+
+    fn set_index(&mut self, position: usize, index: usize) {
+        let per_word = 64 / self.bits;
+        let shift = (position % per_word) * self.bits;
+        let mask: u64 = ((1 << self.bits) - 1) << shift;
+        let word = &mut self.data[position / per_word];
+        *word = (*word & !mask) | ((index as u64) << shift);
+    }
+
+    // doubles the bits per index once the palette does not fit anymore
+    fn grow(&mut self) {
+        let mut grown = Palette::new(self.bits * 2);
+        for position in 0..VOLUME {
+            grown.set_index(position, self.get_index(position));
+        }
+        self.bits = grown.bits;
+        self.data = grown.data;
+    }
+
+    // palette entry of a block, adds it if it is not part of the palette yet
+    fn entry(&mut self, block: Block) -> usize {
+        if let Some(entry) = self.blocks.iter().position(|b| *b == block) {
+            return entry;
+        }
+        if let Some(entry) = self.counts.iter().position(|count| *count == 0) {
+            self.blocks[entry] = block;
+            return entry;
+        }
+        if self.blocks.len() == 1 << self.bits {
+            self.grow();
+        }
+        self.blocks.push(block);
+        self.counts.push(0);
+        self.blocks.len() - 1
+    }
+}
+
+// position inside of a chunk to the position in the storage
+fn flatten(position: [usize; 3]) -> usize {
+    position[0] * 32 * 32 + position[1] * 32 + position[2]
+}
+
+impl BlockStorage {
+    pub fn new(block: Block) -> Self {
+        BlockStorage::Uniform(block)
+    }
+
+    pub fn get(&self, position: [usize; 3]) -> Block {
+        match self {
+            BlockStorage::Uniform(block) => *block,
+            BlockStorage::Palette(palette) => palette.blocks[palette.get_index(flatten(position))],
+        }
+    }
+
+    pub fn set(&mut self, position: [usize; 3], block: Block) {
+        let palette = match self {
+            BlockStorage::Uniform(uniform) if *uniform == block => return,
+            BlockStorage::Uniform(uniform) => {
+                // every position starts with index 0, which is the old block
+                let mut palette = Palette::new(1);
+                palette.blocks.push(*uniform);
+                palette.counts.push(VOLUME as u16);
+                *self = BlockStorage::Palette(palette);
+                match self {
+                    BlockStorage::Palette(palette) => palette,
+                    BlockStorage::Uniform(_) => unreachable!(),
+                }
+            }
+            BlockStorage::Palette(palette) => palette,
+        };
+
+        let position = flatten(position);
+        let old = palette.get_index(position);
+        if palette.blocks[old] == block {
+            return;
+        }
+        palette.counts[old] -= 1;
+
+        let new = palette.entry(block);
+        palette.counts[new] += 1;
+        palette.set_index(position, new);
+
+        // the chunk is made of a single block again
+        if palette.counts[new] as usize == VOLUME {
+            *self = BlockStorage::Uniform(block);
+        }
+    }
+
+    /// the block of the whole chunk if it only consists of one
+    pub fn uniform(&self) -> Option<Block> {
+        match self {
+            BlockStorage::Uniform(block) => Some(*block),
+            BlockStorage::Palette(_) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bits(storage: &BlockStorage) -> usize {
+        match storage {
+            BlockStorage::Uniform(_) => 0,
+            BlockStorage::Palette(palette) => palette.bits,
+        }
+    }
+
+    // a different block for every number, the state keeps them apart without new block ids
+    fn block(number: usize) -> Block {
+        Block {id: 1 + (number / 256) as u16, state: (number % 256) as u8}
+    }
+
+    #[test]
+    fn indices_grow_with_the_palette() {
+        let mut storage = BlockStorage::new(Block::new(0));
+        // together with the air 2, 4, 16, 256 and 301 blocks, each step needs wider indices
+        let position = |number: usize| [number / 32 % 32, number % 32, (number * 7) % 32];
+        for (blocks, expected_bits) in [(1, 1), (3, 2), (15, 4), (255, 8), (300, 16)].iter() {
+            for number in 0..*blocks {
+                storage.set(position(number), block(number));
+            }
+            assert_eq!(bits(&storage), *expected_bits);
+            for number in 0..*blocks {
+                assert_eq!(storage.get(position(number)), block(number));
+            }
+        }
+        assert_eq!(storage.get([31, 31, 31]), Block::new(0));
+    }
+
+    #[test]
+    fn blocks_at_the_edges_round_trip() {
+        let corners = [[0, 0, 0], [31, 0, 0], [0, 31, 0], [0, 0, 31], [31, 31, 0], [31, 0, 31], [0, 31, 31], [31, 31, 31]];
+        let mut storage = BlockStorage::new(Block::new(0));
+        for (number, corner) in corners.iter().enumerate() {
+            storage.set(*corner, block(number));
+        }
+        for (number, corner) in corners.iter().enumerate() {
+            assert_eq!(storage.get(*corner), block(number));
+        }
+        // the neighbours of the corners keep the old block
+        assert_eq!(storage.get([1, 0, 0]), Block::new(0));
+        assert_eq!(storage.get([30, 31, 31]), Block::new(0));
+    }
+
+    #[test]
+    fn freed_entries_are_reused() {
+        let mut storage = BlockStorage::new(Block::new(0));
+        storage.set([1, 2, 3], block(1));
+        storage.set([1, 2, 3], block(2));
+        storage.set([4, 5, 6], block(3));
+        match &storage {
+            BlockStorage::Palette(palette) => {
+                assert_eq!(palette.blocks.len(), 3);
+                assert_eq!(palette.bits, 2);
+            }
+            BlockStorage::Uniform(_) => panic!("the chunk holds three blocks"),
+        }
+        assert_eq!(storage.get([1, 2, 3]), block(2));
+        assert_eq!(storage.get([4, 5, 6]), block(3));
+    }
+
+    #[test]
+    fn single_blocks_collapse_to_uniform() {
+        let mut storage = BlockStorage::new(Block::new(0));
+        storage.set([0, 0, 0], block(1));
+        storage.set([31, 31, 31], block(2));
+        assert_eq!(storage.uniform(), None);
+
+        storage.set([0, 0, 0], Block::new(0));
+        storage.set([31, 31, 31], Block::new(0));
+        assert_eq!(storage.uniform(), Some(Block::new(0)));
+
+        // filling the chunk with another block collapses it as well
+        for x in 0..32 {
+            for y in 0..32 {
+                for z in 0..32 {
+                    storage.set([x, y, z], block(5));
+                }
+            }
+        }
+        assert_eq!(storage.uniform(), Some(block(5)));
+    }
+}
@@ -0,0 +1,142 @@
+use std::collections::{HashSet, VecDeque};
+
+use bevy::prelude::*;
+use bevy::render::camera::{CameraProjection, PerspectiveProjection};
+
+use voxel::culling::connected;
+use voxel::mesh::FACES;
+use voxel::coords::BlockPos;
+use voxel::world::World;
+
+use crate::chunk::ChunkMesh;
+
+/// planes of the camera frustum, a point is inside if `normal.dot(point) + w >= 0` for all of them
+pub struct Frustum {
+    planes: [Vec4; 6],
+}
+impl Frustum {
+    pub fn from_view_projection(view_projection: Mat4) -> Self {
+        let rows = [
+            view_projection.row(0),
+            view_projection.row(1),
+            view_projection.row(2),
+            view_projection.row(3),
+        ];
+
+        // the depth of wgpu ranges from 0 to 1, so the near plane is the third row on its own
+        Frustum {
+            planes: [
+                rows[3] + rows[0],
+                rows[3] - rows[0],
+                rows[3] + rows[1],
+                rows[3] - rows[1],
+                rows[2],
+                rows[3] - rows[2],
+            ],
+        }
+    }
+
+    pub fn intersects_box(&self, min: Vec3, max: Vec3) -> bool {
+        self.planes.iter().all(|plane| {
+            // the corner that lies the furthest in the direction of the plane normal
+            let corner = Vec3::new(
+                if plane.x >= 0.0 { max.x } else { min.x },
+                if plane.y >= 0.0 { max.y } else { min.y },
+                if plane.z >= 0.0 { max.z } else { min.z },
+            );
+            plane.truncate().dot(corner) + plane.w >= 0.0
+        })
+    }
+
+    pub fn intersects_chunk(&self, chunk: [i32; 3]) -> bool {
+        let min = Vec3::new(chunk[0] as f32, chunk[1] as f32, chunk[2] as f32) * 32.0;
+        self.intersects_box(min, min + Vec3::splat(32.0))
+    }
+}
+
+/// walks from the chunk of the camera through the connected faces of the chunks in view,
+/// every chunk that is not reached is hidden
+pub fn visible_chunks(world: &World, camera: Vec3, frustum: &Frustum) -> HashSet<[i32; 3]> {
+    let mut visible: HashSet<[i32; 3]> = HashSet::new();
+
+    let start: [i32; 3] = BlockPos::from_point([camera.x, camera.y, camera.z]).chunk().into();
+
+    // nothing blocks the view from outside of the loaded world
+    if world.get_chunk(start).is_none() {
+        for chunk in world.chunk_index.iter() {
+            if frustum.intersects_chunk([chunk.x, chunk.y, chunk.z]) {
+                visible.insert([chunk.x, chunk.y, chunk.z]);
+            }
+        }
+        return visible;
+    }
+
+    // chunk, face it was entered through and the directions that were taken so far
+    let mut queue: VecDeque<([i32; 3], Option<usize>, u8)> = VecDeque::new();
+    queue.push_back((start, None, 0));
+    visible.insert(start);
+
+    while let Some((position, entered, directions)) = queue.pop_front() {
+        let connections = match world.get_chunk(position) {
+            Some(chunk) => chunk.connections,
+            None => continue,
+        };
+
+        for (face, offset) in FACES.iter().enumerate() {
+            // never walks back towards the camera
+            let opposite = face ^ 1;
+            if directions & (1 << opposite) != 0 {
+                continue;
+            }
+            if let Some(entered) = entered {
+                if !connected(connections, entered, face) {
+                    continue;
+                }
+            }
+
+            let neighbour = [
+                position[0] + offset.normal[0],
+                position[1] + offset.normal[1],
+                position[2] + offset.normal[2],
+            ];
+            if visible.contains(&neighbour)
+                || world.get_chunk(neighbour).is_none()
+                || !frustum.intersects_chunk(neighbour)
+            {
+                continue;
+            }
+
+            visible.insert(neighbour);
+            queue.push_back((neighbour, Some(opposite), directions | (1 << face)));
+        }
+    }
+
+    visible
+}
+
+/// hides the ChunkMeshes outside of the view or behind the terrain
+pub fn cull_chunks(
+    camera: Query<(&Transform, &PerspectiveProjection), With<crate::Camera>>,
+    world: Query<&World, With<World>>,
+    mut chunk_meshes: Query<(&ChunkMesh, &mut Visible)>,
+) {
+    let (camera, projection) = match camera.iter().next() {
+        Some(camera) => camera,
+        None => return,
+    };
+
+    let frustum = Frustum::from_view_projection(
+        projection.get_projection_matrix() * camera.compute_matrix().inverse(),
+    );
+
+    for world in world.iter() {
+        let visible = visible_chunks(world, camera.translation, &frustum);
+
+        for (chunk_mesh, mut visibility) in chunk_meshes.iter_mut() {
+            let is_visible = visible.contains(&[chunk_mesh.x, chunk_mesh.y, chunk_mesh.z]);
+            if visibility.is_visible != is_visible {
+                visibility.is_visible = is_visible;
+            }
+        }
+    }
+}
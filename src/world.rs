@@ -0,0 +1,342 @@
+use std::collections::HashMap;
+
+use noise::NoiseFn;
+use noise::OpenSimplex;
+use noise::Seedable;
+
+use crate::block::{self, Block, BlockId};
+use crate::coords::BlockPos;
+use crate::light;
+use crate::storage::BlockStorage;
+
+pub struct Chunk {
+    pub x: i32,
+    pub z: i32,
+    pub y: i32,
+    pub blocks: BlockStorage,
+    // skylight in the upper and block light in the lower four bits, see `light`
+    pub light: [[[u8; 32]; 32]; 32],
+    // set when the ChunkMesh is outdated
+    pub dirty: bool,
+    // level of detail the ChunkMesh is built with, see `lod`
+    pub lod: usize,
+    // which faces can see each other through the chunk, updated with the ChunkMesh, see `culling`
+    pub connections: u64,
+}
+impl Chunk {
+    //creates a new empty chunk filled with air
+    pub fn new(x: i32, y: i32, z: i32) -> Self {
+        Chunk {
+            x,
+            y,
+            z,
+            blocks: BlockStorage::new(Block::new(block::AIR)),
+            light: [[[0u8; 32]; 32]; 32],
+            dirty: false,
+            lod: 0,
+            connections: u64::MAX,
+        }
+    }
+
+    /// returns the block at a position inside of the chunk
+    pub fn get(&self, position: [usize; 3]) -> Block {
+        self.blocks.get(position)
+    }
+
+    pub fn set(&mut self, position: [usize; 3], block: Block) {
+        self.blocks.set(position, block);
+    }
+}
+
+pub struct World {
+    pub chunk_index: Vec<Chunk>,
+    // position of every chunk in `chunk_index`, filled by `add_chunk`
+    pub chunk_lookup: HashMap<[i32; 3], usize>,
+    pub seed: u32,
+    // height of the highest water block of oceans and lakes
+    pub sea_level: i32,
+    // positions replaced since the last `BlockChanged` events were sent
+    pub changes: Vec<[i32; 3]>,
+}
+impl World {
+    pub fn new(seed: u32, sea_level: i32) -> Self {
+        let chunk_index: Vec<Chunk> = Vec::new();
+
+        World {seed, sea_level, chunk_index, chunk_lookup: HashMap::new(), changes: Vec::new()}
+    }
+
+    pub fn add_chunk(&mut self, chunk: Chunk) -> usize {
+        self.chunk_lookup.insert([chunk.x, chunk.y, chunk.z], self.chunk_index.len());
+        self.chunk_index.push(chunk);
+        self.chunk_index.len() - 1
+    }
+
+    /// drops a chunk, the last chunk of `chunk_index` moves to its index
+    pub fn remove_chunk(&mut self, position: [i32; 3]) -> Option<Chunk> {
+        let index = self.chunk_lookup.remove(&position)?;
+        let chunk = self.chunk_index.swap_remove(index);
+        if let Some(moved) = self.chunk_index.get(index) {
+            self.chunk_lookup.insert([moved.x, moved.y, moved.z], index);
+        }
+        Some(chunk)
+    }
+
+    pub fn get_chunk(&self, position: [i32; 3]) -> Option<&Chunk> {
+        self.chunk_lookup
+            .get(&position)
+            .map(|chunk| &self.chunk_index[*chunk])
+    }
+
+    pub fn get_chunk_mut(&mut self, position: [i32; 3]) -> Option<&mut Chunk> {
+        match self.chunk_lookup.get(&position) {
+            Some(chunk) => Some(&mut self.chunk_index[*chunk]),
+            None => None,
+        }
+    }
+
+    /// returns the block and its state at a world position, `None` if its chunk is not loaded
+    pub fn get(&self, position: [i32; 3]) -> Option<Block> {
+        let (chunk, local) = split_position(position);
+        self.get_chunk(chunk)
+            .map(|chunk| chunk.get(local))
+    }
+
+    /// returns the id of the block at a world position, `None` if its chunk is not loaded
+    pub fn get_block(&self, position: [i32; 3]) -> Option<BlockId> {
+        self.get(position).map(|block| block.id)
+    }
+
+    /// returns the state of the block at a world position, like the fluid level
+    pub fn get_state(&self, position: [i32; 3]) -> u8 {
+        self.get(position).map(|block| block.state).unwrap_or(0)
+    }
+
+    /// replaces the block at a world position, returns false if its chunk is not loaded
+    pub fn set(&mut self, position: [i32; 3], block: Block) -> bool {
+        let (chunk, local) = split_position(position);
+        match self.get_chunk_mut(chunk) {
+            Some(chunk) => chunk.set(local, block),
+            None => return false,
+        }
+        self.mark_dirty(position);
+        self.changes.push(position);
+        true
+    }
+
+    /// replaces the block at a world position with a block of the default state
+    pub fn set_block(&mut self, position: [i32; 3], block: BlockId) -> bool {
+        self.set(position, Block::new(block))
+    }
+
+    /// returns the light at a world position, unloaded chunks are lit by the sky
+    pub fn get_light(&self, position: [i32; 3]) -> u8 {
+        let (chunk, local) = split_position(position);
+        self.get_chunk(chunk)
+            .map(|chunk| chunk.light[local[0]][local[1]][local[2]])
+            .unwrap_or(light::SKY)
+    }
+
+    pub fn set_light(&mut self, position: [i32; 3], light: u8) {
+        let (chunk, local) = split_position(position);
+        if let Some(chunk) = self.get_chunk_mut(chunk) {
+            chunk.light[local[0]][local[1]][local[2]] = light;
+            self.mark_dirty(position);
+        }
+    }
+
+    /// changes the state of a block without replacing it, like `set` the position ends up in `changes`
+    pub fn set_state(&mut self, position: [i32; 3], state: u8) {
+        let (chunk, local) = split_position(position);
+        match self.get_chunk_mut(chunk) {
+            Some(chunk) => {
+                let block = chunk.get(local);
+                if block.state == state {
+                    return;
+                }
+                chunk.set(local, Block { state, ..block });
+            }
+            None => return,
+        }
+        self.mark_dirty(position);
+        self.changes.push(position);
+    }
+
+    // marks the chunk of the position and the neighbours sharing its border for remeshing
+    fn mark_dirty(&mut self, position: [i32; 3]) {
+        let (chunk, local) = split_position(position);
+
+        if let Some(chunk) = self.get_chunk_mut(chunk) {
+            chunk.dirty = true;
+        }
+        for i in 0..3 {
+            let mut neighbour = chunk;
+            if local[i] == 0 {
+                neighbour[i] -= 1;
+            } else if local[i] == 31 {
+                neighbour[i] += 1;
+            } else {
+                continue;
+            }
+            if let Some(neighbour) = self.get_chunk_mut(neighbour) {
+                neighbour.dirty = true;
+            }
+        }
+    }
+}
+
+// splits a world position into the chunk position and the position inside of the chunk
+fn split_position(position: [i32; 3]) -> ([i32; 3], [usize; 3]) {
+    let (chunk, local) = BlockPos::from(position).split();
+    (chunk.into(), local.into())
+}
+
+pub fn generate_terrain(
+    terrain: &mut Chunk,
+    seed: u32,
+    sea_level: i32,
+) {
+    let (chunk_x, chunk_y, chunk_z) = (terrain.x, terrain.y, terrain.z);
+
+    let open_simplex = OpenSimplex::new();
+    open_simplex.set_seed(seed);
+
+    for x in 0..32 {
+        for z in 0..32 {
+            //generates terrain with noise:
+            let height: i32 = (open_simplex.get([
+                ( (x as i32 + chunk_x * 32) as f32 / 15. ) as f64,
+                ( (z as i32 + chunk_z * 32) as f32 / 15. ) as f64,
+            ]) * 15. + 48.0) as i32;
+
+            let height_stalagmites: i32 = (open_simplex.get([
+                ( (x as i32 + chunk_x * 5) as f32 / 5. ) as f64,
+                ( (z as i32 + chunk_z * 5) as f32 / 5. ) as f64,
+            ]) * 42. + 15.) as i32;
+
+            //writes height into terrain block index, the shoreline gets a sand beach:
+            if height <= sea_level + 2 {
+                fill_column(terrain, [x, z], chunk_y, height-3..height, block::SAND);
+            } else {
+                fill_column(terrain, [x, z], chunk_y, height-3..height, block::GRASS);
+            }
+
+            //generates dirt
+            fill_column(terrain, [x, z], chunk_y, height-13..height-3, block::DIRT);
+
+            //generates stone
+            fill_column(terrain, [x, z], chunk_y, -5..height-13, block::STONE);
+
+            //creates stalagmites
+            fill_column(terrain, [x, z], chunk_y, height - 64..height / 2 - 22, block::MAGMA);
+            fill_column(terrain, [x, z], chunk_y, -height_stalagmites..height - 64, block::CRIMSON_STONE);
+
+            //fills the air below the sea level with water
+            fill_column(terrain, [x, z], chunk_y, height..sea_level + 1, block::WATER);
+        }
+    }
+}
+
+// writes a block into every height of the column that is inside of the chunk
+fn fill_column(
+    terrain: &mut Chunk,
+    column: [usize; 2],
+    chunk_y: i32,
+    heights: std::ops::Range<i32>,
+    block: BlockId,
+) {
+    for height in heights {
+        if height >= chunk_y*32
+        && height <= chunk_y*32 + 31 {
+            terrain.set([column[0], (height - chunk_y*32) as usize, column[1]], Block::new(block));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    // the eight chunks around the origin, so that every axis crosses into negative positions
+    fn world() -> World {
+        let mut world = World::new(0, 0);
+        for x in -1..1 {
+            for y in -1..1 {
+                for z in -1..1 {
+                    world.add_chunk(Chunk::new(x, y, z));
+                }
+            }
+        }
+        world
+    }
+
+    #[test]
+    fn blocks_outside_of_the_loaded_chunks() {
+        let mut world = world();
+        assert_eq!(world.get_block([-33, 0, 0]), None);
+        assert_eq!(world.get_block([32, 0, 0]), None);
+        assert!(!world.set_block([0, -33, 0], block::STONE));
+        assert!(world.changes.is_empty());
+    }
+
+    #[test]
+    fn removed_chunks_keep_the_others_in_place() {
+        let mut world = world();
+        world.set_block([-1, -1, -1], block::STONE);
+        let removed = world.remove_chunk([-1, 0, 0]).unwrap();
+        assert_eq!([removed.x, removed.y, removed.z], [-1, 0, 0]);
+        assert!(world.remove_chunk([-1, 0, 0]).is_none());
+        assert_eq!(world.get_block([-1, 5, 5]), None);
+        assert_eq!(world.get_block([-1, -1, -1]), Some(block::STONE));
+        for (index, chunk) in world.chunk_index.iter().enumerate() {
+            assert_eq!(world.chunk_lookup[&[chunk.x, chunk.y, chunk.z]], index);
+        }
+    }
+
+    #[test]
+    fn state_changes_are_recorded() {
+        let mut world = world();
+        world.set_state([3, 4, 5], 0);
+        assert!(world.changes.is_empty());
+        world.set_state([3, 4, 5], 2);
+        assert_eq!(world.changes, vec![[3, 4, 5]]);
+        assert_eq!(world.get_state([3, 4, 5]), 2);
+    }
+
+    #[test]
+    fn edits_on_a_border_mark_the_neighbour() {
+        let mut world = world();
+        world.set_block([-1, 5, 5], block::STONE);
+        assert!(world.get_chunk([-1, 0, 0]).unwrap().dirty);
+        assert!(world.get_chunk([0, 0, 0]).unwrap().dirty);
+        assert!(!world.get_chunk([0, -1, 0]).unwrap().dirty);
+    }
+
+    proptest! {
+        #[test]
+        fn blocks_are_stored_at_their_position(x in -32..32, y in -32..32, z in -32..32) {
+            let mut world = world();
+            prop_assert!(world.set_block([x, y, z], block::STONE));
+            prop_assert_eq!(world.get_block([x, y, z]), Some(block::STONE));
+
+            let (chunk, local) = split_position([x, y, z]);
+            prop_assert_eq!(world.get_chunk(chunk).unwrap().get(local).id, block::STONE);
+
+            // no other block of the world changed
+            let stone = world.chunk_index.iter()
+                .map(|chunk| {
+                    let mut count = 0;
+                    for x in 0..32 {
+                        for y in 0..32 {
+                            for z in 0..32 {
+                                count += (chunk.get([x, y, z]).id == block::STONE) as usize;
+                            }
+                        }
+                    }
+                    count
+                })
+                .sum::<usize>();
+            prop_assert_eq!(stone, 1);
+        }
+    }
+}
@@ -0,0 +1,151 @@
+//! connects the client to a stand-in server on loopback that answers with prepared messages
+
+use std::io::{BufReader, Write};
+use std::net::{SocketAddr, TcpListener};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+use voxel::block::{self, Block};
+use voxel::client::{Connection, Prediction};
+use voxel::protocol::{self, ClientMessage, ServerMessage};
+use voxel::world::{Chunk, World};
+
+// accepts one client, answers its hello with `answer` and hands everything it sends to the test
+fn stand_in_server(answer: Vec<ServerMessage>) -> (SocketAddr, mpsc::Receiver<ClientMessage>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    let (sender, received) = mpsc::channel();
+
+    std::thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut writer = stream;
+
+        let hello = protocol::read_client_message(&mut reader).unwrap();
+        sender.send(hello).unwrap();
+        for message in answer.iter() {
+            protocol::write_server_message(&mut writer, message).unwrap();
+        }
+        writer.flush().unwrap();
+
+        while let Ok(message) = protocol::read_client_message(&mut reader) {
+            if sender.send(message).is_err() {
+                return;
+            }
+        }
+    });
+    (address, received)
+}
+
+fn welcome() -> ServerMessage {
+    ServerMessage::Welcome {player: 4, seed: 7, sea_level: 30, spawn: [0.0, 50.0, 0.0]}
+}
+
+// polls the connection until `count` messages arrived
+fn receive(connection: &mut Connection, count: usize) -> Vec<ServerMessage> {
+    let deadline = Instant::now() + Duration::from_secs(10);
+    let mut messages = Vec::new();
+    while messages.len() < count && Instant::now() < deadline {
+        messages.extend(connection.receive());
+        std::thread::sleep(Duration::from_millis(5));
+    }
+    messages
+}
+
+#[test]
+fn joins_and_receives_chunks() {
+    let mut chunk = Chunk::new(0, 1, -1);
+    chunk.set([1, 2, 3], Block::new(block::STONE));
+    let chunk_message = ServerMessage::Chunk {position: [0, 1, -1], blocks: protocol::encode_chunk(&chunk)};
+
+    let (address, sent) = stand_in_server(vec![welcome(), chunk_message.clone()]);
+    let mut connection = Connection::connect(address, "carol").unwrap();
+
+    assert_eq!(connection.welcome.player, 4);
+    assert_eq!(connection.welcome.seed, 7);
+    assert_eq!(sent.recv().unwrap(), ClientMessage::Hello {version: protocol::VERSION, name: "carol".to_string()});
+    assert_eq!(receive(&mut connection, 1), vec![chunk_message]);
+
+    connection.send(&ClientMessage::Move {position: [1.0, 2.0, 3.0], rotation: [0.0, 0.0, 0.0, 1.0]});
+    connection.flush();
+    let moved = sent.recv_timeout(Duration::from_secs(10)).unwrap();
+    assert_eq!(moved, ClientMessage::Move {position: [1.0, 2.0, 3.0], rotation: [0.0, 0.0, 0.0, 1.0]});
+}
+
+#[test]
+fn rejections_fail_to_connect() {
+    let (address, _sent) = stand_in_server(vec![ServerMessage::Rejected {reason: "full".to_string()}]);
+    let error = Connection::connect(address, "dave").err().expect("the server rejected the client");
+    assert!(error.to_string().contains("full"));
+}
+
+#[test]
+fn closed_connections_are_noticed() {
+    let (address, sent) = stand_in_server(vec![welcome()]);
+    let mut connection = Connection::connect(address, "erin").unwrap();
+    // ends the thread of the stand-in server, which closes the connection
+    drop(sent);
+    connection.send(&ClientMessage::Move {position: [0.0; 3], rotation: [0.0, 0.0, 0.0, 1.0]});
+    connection.flush();
+
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while !connection.is_closed() && Instant::now() < deadline {
+        connection.receive();
+        std::thread::sleep(Duration::from_millis(5));
+    }
+    assert!(connection.is_closed());
+}
+
+fn world() -> World {
+    let mut world = World::new(0, 0);
+    world.add_chunk(Chunk::new(0, 0, 0));
+    world
+}
+
+#[test]
+fn predicted_edits_show_up_right_away() {
+    let mut world = world();
+    let mut prediction = Prediction::default();
+
+    assert!(prediction.predict(&mut world, [1, 1, 1], Block::new(block::STONE)));
+    assert_eq!(world.get_block([1, 1, 1]), Some(block::STONE));
+    // placing the same block again does not need to be sent
+    assert!(!prediction.predict(&mut world, [1, 1, 1], Block::new(block::STONE)));
+    // neither do edits outside of the received chunks
+    assert!(!prediction.predict(&mut world, [-1, 1, 1], Block::new(block::STONE)));
+
+    prediction.confirm(&mut world, [1, 1, 1], Block::new(block::STONE));
+    assert!(!prediction.is_pending([1, 1, 1]));
+    assert_eq!(world.get_block([1, 1, 1]), Some(block::STONE));
+}
+
+#[test]
+fn rejected_edits_are_rolled_back() {
+    let mut world = world();
+    let mut prediction = Prediction::default();
+
+    prediction.predict(&mut world, [2, 2, 2], Block::new(block::GLASS));
+    prediction.confirm(&mut world, [2, 2, 2], Block::new(block::AIR));
+    assert_eq!(world.get_block([2, 2, 2]), Some(block::AIR));
+}
+
+#[test]
+fn older_answers_do_not_undo_newer_edits() {
+    let mut world = world();
+    let mut prediction = Prediction::default();
+
+    prediction.predict(&mut world, [3, 3, 3], Block::new(block::GLASS));
+    prediction.predict(&mut world, [3, 3, 3], Block::new(block::AIR));
+    prediction.predict(&mut world, [3, 3, 3], Block::new(block::DIRT));
+
+    // the answers to the first two edits arrive before the last one
+    prediction.confirm(&mut world, [3, 3, 3], Block::new(block::GLASS));
+    prediction.confirm(&mut world, [3, 3, 3], Block::new(block::AIR));
+    assert_eq!(world.get_block([3, 3, 3]), Some(block::DIRT));
+
+    prediction.confirm(&mut world, [3, 3, 3], Block::new(block::DIRT));
+    assert!(!prediction.is_pending([3, 3, 3]));
+    // edits of other players go through once nothing is pending
+    prediction.confirm(&mut world, [3, 3, 3], Block::new(block::SAND));
+    assert_eq!(world.get_block([3, 3, 3]), Some(block::SAND));
+}
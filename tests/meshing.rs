@@ -0,0 +1,212 @@
+//! compares the meshes of fixture chunks with the golden files in `tests/golden`,
+//! run with `UPDATE_GOLDEN=1` to write them again after an intended change of the mesher
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::path::PathBuf;
+
+use voxel::atlas::Tiles;
+use voxel::block::{self, Block, BlockId};
+use voxel::custom_model::CustomModels;
+use voxel::light;
+use voxel::mesh::{self, MeshBuffers, MeshPass};
+use voxel::model;
+use voxel::world::{Chunk, World};
+
+// meshes with at most this many vertices are written out vertex by vertex
+const LISTED_VERTICES: usize = 96;
+
+const FACE_NAMES: [&str; 6] = ["-y", "+y", "-z", "+z", "-x", "+x"];
+const PASSES: [(MeshPass, &str); 3] = [
+    (MeshPass::Opaque, "opaque"),
+    (MeshPass::Cutout, "cutout"),
+    (MeshPass::Translucent, "translucent"),
+];
+
+// every texture gets its own tile, so that a wrong texture shows up in the golden files
+fn tiles() -> Tiles {
+    let mut tiles = Tiles::default();
+    let names = [
+        "grass", "dirt", "stone", "crimson_stone", "magma", "water", "sand", "lava",
+        "gravel", "torch", "planks", "flower", "glass", "leaves", "portal",
+    ];
+    for (i, name) in names.iter().enumerate() {
+        tiles.tiles.insert(name.to_string(), i as u16 + 1);
+    }
+    tiles
+}
+
+// a lit world of empty chunks with the given blocks
+fn world(chunks: &[[i32; 3]], blocks: &[([i32; 3], Block)]) -> World {
+    let mut world = World::new(0, 0);
+    for chunk in chunks.iter() {
+        world.add_chunk(Chunk::new(chunk[0], chunk[1], chunk[2]));
+    }
+    for (position, block) in blocks.iter() {
+        assert!(world.set(*position, *block), "{:?} is outside of the fixture", position);
+    }
+    world.changes.clear();
+    light::light_world(&mut world);
+    world
+}
+
+fn describe(buffers: &MeshBuffers) -> String {
+    let mut text = String::new();
+    writeln!(text, "vertices {}", buffers.vertices.len()).unwrap();
+    writeln!(text, "indices {}", buffers.indices.len()).unwrap();
+
+    let vertices: Vec<mesh::Vertex> = buffers.vertices.iter().map(|vertex| mesh::unpack_vertex(*vertex)).collect();
+
+    let mut faces = [0; 6];
+    let mut uvs: BTreeMap<String, usize> = BTreeMap::new();
+    let mut ao = [0; 4];
+    for vertex in vertices.iter() {
+        faces[vertex.face] += 1;
+        *uvs.entry(format!("{},{}", vertex.uv[0], vertex.uv[1])).or_default() += 1;
+        ao[vertex.ao as usize] += 1;
+    }
+
+    let faces: Vec<String> = FACE_NAMES.iter().zip(faces.iter()).map(|(name, count)| format!("{} {}", name, count)).collect();
+    writeln!(text, "face vertices {}", faces.join(" ")).unwrap();
+    let uvs: Vec<String> = uvs.iter().map(|(uv, count)| format!("{} {}", uv, count)).collect();
+    writeln!(text, "uvs {}", uvs.join(" ")).unwrap();
+    writeln!(text, "ao {} {} {} {}", ao[0], ao[1], ao[2], ao[3]).unwrap();
+
+    if vertices.len() <= LISTED_VERTICES {
+        for vertex in vertices.iter() {
+            writeln!(
+                text,
+                "vertex {},{},{} {} tile {} uv {},{} light {:02x} ao {}",
+                vertex.position[0], vertex.position[1], vertex.position[2],
+                FACE_NAMES[vertex.face], vertex.tile, vertex.uv[0], vertex.uv[1], vertex.light, vertex.ao,
+            ).unwrap();
+        }
+        let triangles: Vec<String> = buffers.indices.chunks(3).map(|triangle| format!("{:?}", triangle)).collect();
+        writeln!(text, "triangles {}", triangles.join(" ")).unwrap();
+    }
+    text
+}
+
+// meshes all passes of the given chunks and compares them with `tests/golden/<name>.txt`
+fn check(name: &str, world: &World, chunks: &[[i32; 3]]) {
+    let models = CustomModels::default();
+    let tiles = tiles();
+    let mut text = String::new();
+
+    for position in chunks.iter() {
+        let chunk = world.chunk_lookup[position];
+        for (pass, pass_name) in PASSES.iter() {
+            let buffers = mesh::create_chunk_mesh(chunk, world, *pass, &models, &tiles);
+
+            assert_eq!(buffers.indices.len() % 3, 0, "{} has an incomplete triangle", name);
+            assert!(
+                buffers.indices.iter().all(|index| (*index as usize) < buffers.vertices.len()),
+                "{} has indices without a vertex",
+                name,
+            );
+            // empty passes are left out, so adding a pass does not touch every golden file
+            if buffers.vertices.is_empty() {
+                continue;
+            }
+
+            writeln!(text, "== chunk {:?} {}", position, pass_name).unwrap();
+            text.push_str(&describe(&buffers));
+        }
+    }
+
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/golden").join(format!("{}.txt", name));
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, &text).unwrap();
+        return;
+    }
+
+    let golden = std::fs::read_to_string(&path)
+        .unwrap_or_else(|e| panic!("could not read {}: {}, run with UPDATE_GOLDEN=1 to create it", path.display(), e));
+    assert!(
+        golden == text,
+        "the mesh of {} differs from {}, run with UPDATE_GOLDEN=1 if the change is intended\n--- expected\n{}\n--- found\n{}",
+        name,
+        path.display(),
+        golden,
+        text,
+    );
+}
+
+fn block(id: BlockId) -> Block {
+    Block::new(id)
+}
+
+#[test]
+fn single_block() {
+    let world = world(&[[0, 0, 0]], &[([5, 5, 5], block(block::STONE))]);
+    check("single_block", &world, &[[0, 0, 0]]);
+}
+
+#[test]
+fn checkerboard() {
+    let mut blocks = Vec::new();
+    for x in 0..32 {
+        for y in 0..32 {
+            for z in 0..32 {
+                if (x + y + z) % 2 == 0 {
+                    blocks.push(([x, y, z], block(block::STONE)));
+                }
+            }
+        }
+    }
+    let world = world(&[[0, 0, 0]], &blocks);
+    check("checkerboard", &world, &[[0, 0, 0]]);
+}
+
+#[test]
+fn full_chunk() {
+    let mut blocks = Vec::new();
+    for x in 0..32 {
+        for y in 0..32 {
+            for z in 0..32 {
+                blocks.push(([x, y, z], block(block::DIRT)));
+            }
+        }
+    }
+    let world = world(&[[0, 0, 0]], &blocks);
+    check("full_chunk", &world, &[[0, 0, 0]]);
+}
+
+// faces between two loaded chunks are hidden, faces towards unloaded chunks are drawn,
+// the corners next to the border are darkened by the blocks of the neighbour
+#[test]
+fn chunk_border() {
+    let chunks = [[-1, 0, 0], [0, 0, 0]];
+    let world = world(&chunks, &[
+        ([-1, 4, 4], block(block::STONE)),
+        ([0, 4, 4], block(block::STONE)),
+        ([-1, 9, 9], block(block::SAND)),
+        ([0, 10, 9], block(block::GRAVEL)),
+        ([31, 20, 20], block(block::GRASS)),
+    ]);
+    check("chunk_border", &world, &chunks);
+}
+
+#[test]
+fn render_passes() {
+    let world = world(&[[0, 0, 0]], &[
+        ([2, 1, 2], block(block::WATER)),
+        ([4, 1, 4], block(block::GLASS)),
+        ([6, 1, 6], block(block::LEAVES)),
+        ([8, 1, 8], block(block::FLOWER)),
+        ([10, 1, 10], block(block::TORCH)),
+    ]);
+    check("render_passes", &world, &[[0, 0, 0]]);
+}
+
+#[test]
+fn shapes() {
+    let world = world(&[[0, 0, 0]], &[
+        ([2, 1, 2], block(block::STONE_SLAB)),
+        ([4, 1, 4], Block {id: block::STONE_STAIRS, state: model::placement_state(block::STONE_STAIRS, [1.0, 0.0, 0.0])}),
+        ([6, 1, 6], block(block::FENCE)),
+        ([7, 1, 6], block(block::STONE)),
+    ]);
+    check("shapes", &world, &[[0, 0, 0]]);
+}
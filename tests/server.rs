@@ -0,0 +1,207 @@
+//! runs a server on loopback and talks to it with several clients
+
+use std::io::BufReader;
+use std::net::{SocketAddr, TcpStream};
+use std::time::{Duration, Instant};
+
+use voxel::block::{self, Block};
+use voxel::protocol::{self, ClientMessage, ServerMessage, VERSION};
+use voxel::server::{Server, ServerSettings};
+
+fn start_server() -> SocketAddr {
+    let settings = ServerSettings {view_distance: 1, heights: 0..3, ..Default::default()};
+    let server = Server::bind("127.0.0.1:0", settings).unwrap();
+    let address = server.local_addr().unwrap();
+    std::thread::spawn(move || server.run());
+    address
+}
+
+struct TestClient {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+}
+impl TestClient {
+    fn connect(address: SocketAddr) -> Self {
+        let stream = TcpStream::connect(address).unwrap();
+        stream.set_read_timeout(Some(Duration::from_secs(10))).unwrap();
+        TestClient {reader: BufReader::new(stream.try_clone().unwrap()), writer: stream}
+    }
+
+    fn send(&mut self, message: ClientMessage) {
+        protocol::write_client_message(&mut self.writer, &message).unwrap();
+    }
+
+    // skips messages until one matches
+    fn expect<T>(&mut self, mut matches: impl FnMut(ServerMessage) -> Option<T>) -> T {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while Instant::now() < deadline {
+            let message = protocol::read_server_message(&mut self.reader).expect("connection closed while waiting");
+            if let Some(result) = matches(message) {
+                return result;
+            }
+        }
+        panic!("the expected message did not arrive");
+    }
+
+    // joins and returns the id of the player
+    fn join(address: SocketAddr, name: &str) -> (Self, u32) {
+        let mut client = TestClient::connect(address);
+        client.send(ClientMessage::Hello {version: VERSION, name: name.to_string()});
+        let player = client.expect(|message| match message {
+            ServerMessage::Welcome { player, .. } => Some(player),
+            _ => None,
+        });
+        (client, player)
+    }
+}
+
+#[test]
+fn other_versions_are_rejected() {
+    let address = start_server();
+    let mut client = TestClient::connect(address);
+    client.send(ClientMessage::Hello {version: VERSION + 1, name: "old".to_string()});
+    match protocol::read_server_message(&mut client.reader).unwrap() {
+        ServerMessage::Rejected { .. } => {}
+        message => panic!("expected a rejection, got {:?}", message),
+    }
+}
+
+#[test]
+fn chunks_are_streamed_to_new_players() {
+    let address = start_server();
+    let (mut client, _) = TestClient::join(address, "alice");
+
+    let (position, blocks) = client.expect(|message| match message {
+        ServerMessage::Chunk { position, blocks } => Some((position, blocks)),
+        _ => None,
+    });
+    let chunk = protocol::decode_chunk(position, &blocks).unwrap();
+    assert_eq!([chunk.x, chunk.y, chunk.z], position);
+
+    // the chunks around the spawn are dropped once the player is far enough away,
+    // the server only lets it move a bit every tick, so the move is repeated until it arrived
+    let mut writer = client.writer.try_clone().unwrap();
+    std::thread::spawn(move || {
+        let far = ClientMessage::Move {position: [200.0, 65.0, 0.0], rotation: [0.0, 0.0, 0.0, 1.0]};
+        for _ in 0..100 {
+            if protocol::write_client_message(&mut writer, &far).is_err() {
+                return;
+            }
+            std::thread::sleep(Duration::from_millis(100));
+        }
+    });
+    let unloaded = client.expect(|message| match message {
+        ServerMessage::ChunkUnloaded { position } => Some(position),
+        _ => None,
+    });
+    assert!(unloaded[0] <= 3);
+}
+
+#[test]
+fn edits_and_movement_are_shared() {
+    let address = start_server();
+    let (mut alice, alice_id) = TestClient::join(address, "alice");
+    let (mut bob, _) = TestClient::join(address, "bob");
+
+    alice.expect(|message| match message {
+        ServerMessage::PlayerJoined { name, .. } if name == "bob" => Some(()),
+        _ => None,
+    });
+
+    // the spawn lies at [0, 65, 0], a block far away is out of reach and comes back unchanged
+    let far = [0, 40, 20];
+    alice.send(ClientMessage::SetBlock {position: far, block: Block::new(block::GLASS)});
+    let rejected = alice.expect(|message| match message {
+        ServerMessage::BlockChanged { position, block } if position == far => Some(block),
+        _ => None,
+    });
+    assert_ne!(rejected.id, block::GLASS);
+
+    let near = [0, 63, 0];
+    alice.send(ClientMessage::SetBlock {position: near, block: Block::new(block::GLASS)});
+    let changed = bob.expect(|message| match message {
+        ServerMessage::BlockChanged { position, block } => Some((position, block)),
+        _ => None,
+    });
+    assert_eq!(changed, (near, Block::new(block::GLASS)));
+
+    alice.send(ClientMessage::Move {position: [1.0, 66.0, 2.0], rotation: [0.0, 0.0, 0.0, 1.0]});
+    let moved = bob.expect(|message| match message {
+        ServerMessage::PlayerMoved { player, position, .. } => Some((player, position)),
+        _ => None,
+    });
+    assert_eq!(moved, (alice_id, [1.0, 66.0, 2.0]));
+
+    drop(alice);
+    let left = bob.expect(|message| match message {
+        ServerMessage::PlayerLeft { player } => Some(player),
+        _ => None,
+    });
+    assert_eq!(left, alice_id);
+}
+
+#[test]
+fn sand_falls_and_water_flows_for_everyone() {
+    let address = start_server();
+    let (mut alice, _) = TestClient::join(address, "alice");
+
+    // both are placed in the air above the spawn
+    let sand = [0, 68, 0];
+    alice.send(ClientMessage::SetBlock {position: sand, block: Block::new(block::SAND)});
+    let landed = alice.expect(|message| match message {
+        ServerMessage::BlockChanged { position, block } if block.id == block::SAND && position != sand => Some(position),
+        _ => None,
+    });
+    assert!(landed[1] < sand[1]);
+
+    // the level of the water below the source is a state change
+    let water = [2, 68, 0];
+    alice.send(ClientMessage::SetBlock {position: water, block: Block::new(block::WATER)});
+    alice.expect(|message| match message {
+        ServerMessage::BlockChanged { position, block } if position == [2, 67, 0] && block.id == block::WATER && block.state != 0 => Some(()),
+        _ => None,
+    });
+}
+
+#[test]
+fn edits_are_kept_in_the_world_directory() {
+    let directory = std::env::temp_dir().join(format!("voxel-server-world-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&directory);
+    let start = |seed| {
+        let settings = ServerSettings {seed, view_distance: 1, heights: 0..3, world: Some(directory.clone()), ..Default::default()};
+        let server = Server::bind("127.0.0.1:0", settings).unwrap();
+        let address = server.local_addr().unwrap();
+        std::thread::spawn(move || server.run());
+        address
+    };
+
+    let (mut alice, _) = TestClient::join(start(7), "alice");
+    let near = [0, 63, 0];
+    alice.send(ClientMessage::SetBlock {position: near, block: Block::new(block::GLASS)});
+    alice.expect(|message| match message {
+        ServerMessage::BlockChanged { position, .. } if position == near => Some(()),
+        _ => None,
+    });
+
+    // changed chunks are written every few seconds
+    let saved = directory.join("chunks").join("0_1_0.bin");
+    let deadline = Instant::now() + Duration::from_secs(15);
+    while !saved.exists() && Instant::now() < deadline {
+        std::thread::sleep(Duration::from_millis(50));
+    }
+    assert!(saved.exists());
+
+    // a second server on the same directory keeps the seed of the world and the edit
+    let mut bob = TestClient::connect(start(8));
+    bob.send(ClientMessage::Hello {version: VERSION, name: "bob".to_string()});
+    let seed = bob.expect(|message| match message {
+        ServerMessage::Welcome { seed, .. } => Some(seed),
+        _ => None,
+    });
+    assert_eq!(seed, 7);
+    let chunk = bob.expect(|message| match message {
+        ServerMessage::Chunk { position, blocks } if position == [0, 1, 0] => Some(protocol::decode_chunk(position, &blocks).unwrap()),
+        _ => None,
+    });
+    assert_eq!(chunk.get([0, 31, 0]), Block::new(block::GLASS));
+}